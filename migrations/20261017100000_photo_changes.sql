@@ -0,0 +1,62 @@
+-- Journal of every change made to the photos visible to a user, used for delta syncing.
+-- The id of a change is used as the sync token, AUTOINCREMENT guarantees it is never reused.
+CREATE TABLE photo_changes
+(
+    id       INTEGER NOT NULL PRIMARY KEY AUTOINCREMENT,
+    photo_id INTEGER NOT NULL,
+    user_id  TEXT    NOT NULL,
+    -- One of 'upsert', 'delete', 'favorite' or 'unfavorite'
+    kind     TEXT    NOT NULL
+);
+
+CREATE INDEX photo_changes_user_id_index ON photo_changes (user_id, id);
+
+CREATE TRIGGER photos_insert_change
+    AFTER INSERT
+    ON photos
+BEGIN
+    INSERT INTO photo_changes (photo_id, user_id, kind) VALUES (new.id, new.user_id, 'upsert');
+END;
+
+CREATE TRIGGER photos_update_change
+    AFTER UPDATE
+    ON photos
+BEGIN
+    -- A photo moved to another user disappears for the previous one
+    INSERT INTO photo_changes (photo_id, user_id, kind)
+    SELECT old.id, old.user_id, 'delete'
+    WHERE old.user_id != new.user_id;
+
+    INSERT INTO photo_changes (photo_id, user_id, kind) VALUES (new.id, new.user_id, 'upsert');
+END;
+
+CREATE TRIGGER photos_delete_change
+    AFTER DELETE
+    ON photos
+BEGIN
+    INSERT INTO photo_changes (photo_id, user_id, kind) VALUES (old.id, old.user_id, 'delete');
+END;
+
+CREATE TRIGGER favorite_photos_insert_change
+    AFTER INSERT
+    ON favorite_photos
+BEGIN
+    INSERT INTO photo_changes (photo_id, user_id, kind) VALUES (new.photo_id, new.user_id, 'favorite');
+END;
+
+CREATE TRIGGER favorite_photos_delete_change
+    AFTER DELETE
+    ON favorite_photos
+BEGIN
+    INSERT INTO photo_changes (photo_id, user_id, kind) VALUES (old.photo_id, old.user_id, 'unfavorite');
+END;
+
+-- Existing data is journaled so that syncing from scratch returns everything
+INSERT INTO photo_changes (photo_id, user_id, kind)
+SELECT id, user_id, 'upsert'
+FROM photos
+ORDER BY id;
+
+INSERT INTO photo_changes (photo_id, user_id, kind)
+SELECT photo_id, user_id, 'favorite'
+FROM favorite_photos;
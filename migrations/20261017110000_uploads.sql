@@ -0,0 +1,15 @@
+-- Resumable uploads in progress, their data is staged in ${STORAGE_PATH}/.uploads/<id>
+CREATE TABLE uploads
+(
+    id            TEXT     NOT NULL PRIMARY KEY,
+    user_id       TEXT     NOT NULL,
+    name          TEXT     NOT NULL,
+    folder        TEXT,
+    time_created  DATETIME NOT NULL,
+    make_public   BOOLEAN  NOT NULL,
+    upload_length INTEGER  NOT NULL,
+    upload_offset INTEGER  NOT NULL,
+    started_at    DATETIME NOT NULL,
+
+    FOREIGN KEY (user_id) REFERENCES users (id)
+);
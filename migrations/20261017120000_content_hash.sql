@@ -0,0 +1,4 @@
+-- Hex encoded SHA-256 of the file, used to detect duplicate uploads
+ALTER TABLE photos ADD COLUMN content_hash TEXT;
+
+CREATE INDEX photos_content_hash_index ON photos (content_hash);
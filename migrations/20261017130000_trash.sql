@@ -0,0 +1,20 @@
+-- Deleted photos are kept in ${STORAGE_PATH}/.trash until they are restored or purged
+ALTER TABLE photos ADD COLUMN trashed_at DATETIME;
+
+CREATE INDEX photos_trashed_at_index ON photos (trashed_at) WHERE trashed_at IS NOT NULL;
+
+-- Trashed photos are reported as deleted until they are restored
+DROP TRIGGER photos_update_change;
+
+CREATE TRIGGER photos_update_change
+    AFTER UPDATE
+    ON photos
+BEGIN
+    -- A photo moved to another user disappears for the previous one
+    INSERT INTO photo_changes (photo_id, user_id, kind)
+    SELECT old.id, old.user_id, 'delete'
+    WHERE old.user_id != new.user_id;
+
+    INSERT INTO photo_changes (photo_id, user_id, kind)
+    VALUES (new.id, new.user_id, CASE WHEN new.trashed_at IS NULL THEN 'upsert' ELSE 'delete' END);
+END;
@@ -0,0 +1,28 @@
+-- Albums group photos without moving their files, a photo can be in any number of albums
+CREATE TABLE albums
+(
+    id             INTEGER  NOT NULL PRIMARY KEY,
+    user_id        TEXT     NOT NULL,
+    name           TEXT     NOT NULL,
+    description    TEXT,
+    cover_photo_id INTEGER,
+    created_at     DATETIME NOT NULL,
+
+    FOREIGN KEY (user_id) REFERENCES users (id),
+    FOREIGN KEY (cover_photo_id) REFERENCES photos (id) ON DELETE SET NULL
+);
+
+CREATE INDEX albums_user_id_index ON albums (user_id);
+
+CREATE TABLE album_photos
+(
+    album_id INTEGER NOT NULL,
+    photo_id INTEGER NOT NULL,
+    -- Manual ordering of the photos inside the album, ascending
+    position INTEGER NOT NULL,
+    PRIMARY KEY (album_id, photo_id),
+    FOREIGN KEY (album_id) REFERENCES albums (id) ON DELETE CASCADE,
+    FOREIGN KEY (photo_id) REFERENCES photos (id) ON DELETE CASCADE
+);
+
+CREATE INDEX album_photos_photo_id_index ON album_photos (photo_id);
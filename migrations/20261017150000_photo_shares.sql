@@ -0,0 +1,54 @@
+-- Grants giving a user access to a single photo or to a whole folder of another user
+CREATE TABLE photo_shares
+(
+    id         INTEGER  NOT NULL PRIMARY KEY,
+    -- The user who shared the photos
+    owner_id   TEXT     NOT NULL,
+    -- The user the photos are shared with
+    user_id    TEXT     NOT NULL,
+    photo_id   INTEGER,
+    folder     TEXT,
+    -- One of 'read' or 'contribute'
+    permission TEXT     NOT NULL,
+    created_at DATETIME NOT NULL,
+
+    CHECK ((photo_id IS NULL) != (folder IS NULL)),
+    FOREIGN KEY (owner_id) REFERENCES users (id),
+    FOREIGN KEY (user_id) REFERENCES users (id),
+    FOREIGN KEY (photo_id) REFERENCES photos (id) ON DELETE CASCADE
+);
+
+CREATE UNIQUE INDEX photo_shares_photo_index ON photo_shares (user_id, photo_id) WHERE photo_id IS NOT NULL;
+CREATE UNIQUE INDEX photo_shares_folder_index ON photo_shares (user_id, owner_id, folder) WHERE folder IS NOT NULL;
+CREATE INDEX photo_shares_owner_id_index ON photo_shares (owner_id);
+
+-- Every photo shared with a user, a photo can appear multiple times with different permissions
+CREATE VIEW shared_photos AS
+SELECT photos.id AS photo_id, photo_shares.user_id, photo_shares.permission
+FROM photo_shares
+         JOIN photos ON photos.id = photo_shares.photo_id AND photos.user_id = photo_shares.owner_id
+UNION ALL
+SELECT photos.id AS photo_id, photo_shares.user_id, photo_shares.permission
+FROM photo_shares
+         JOIN photos ON photos.user_id = photo_shares.owner_id AND photos.folder = photo_shares.folder;
+
+-- The photos appear for the user when shared and disappear when the share is revoked
+CREATE TRIGGER photo_shares_insert_change
+    AFTER INSERT
+    ON photo_shares
+BEGIN
+    INSERT INTO photo_changes (photo_id, user_id, kind)
+    SELECT id, new.user_id, 'upsert'
+    FROM photos
+    WHERE user_id = new.owner_id AND (id = new.photo_id OR folder = new.folder);
+END;
+
+CREATE TRIGGER photo_shares_delete_change
+    AFTER DELETE
+    ON photo_shares
+BEGIN
+    INSERT INTO photo_changes (photo_id, user_id, kind)
+    SELECT id, old.user_id, 'delete'
+    FROM photos
+    WHERE user_id = old.owner_id AND (id = old.photo_id OR folder = old.folder);
+END;
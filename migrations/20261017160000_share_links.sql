@@ -0,0 +1,27 @@
+-- Links giving anyone with the token access to a folder or a selection of photos of a user
+CREATE TABLE share_links
+(
+    id             INTEGER  NOT NULL PRIMARY KEY,
+    token          TEXT     NOT NULL UNIQUE,
+    -- The user who created the link
+    user_id        TEXT     NOT NULL,
+    -- The shared folder of the user, the photos are in share_link_photos when NULL
+    folder         TEXT,
+    expires_at     DATETIME,
+    password_hash  TEXT,
+    allow_download BOOLEAN  NOT NULL,
+    created_at     DATETIME NOT NULL,
+
+    FOREIGN KEY (user_id) REFERENCES users (id)
+);
+
+CREATE INDEX share_links_user_id_index ON share_links (user_id);
+
+CREATE TABLE share_link_photos
+(
+    link_id  INTEGER NOT NULL,
+    photo_id INTEGER NOT NULL,
+    PRIMARY KEY (link_id, photo_id),
+    FOREIGN KEY (link_id) REFERENCES share_links (id) ON DELETE CASCADE,
+    FOREIGN KEY (photo_id) REFERENCES photos (id) ON DELETE CASCADE
+);
@@ -0,0 +1,23 @@
+-- EXIF metadata extracted once when a photo is added, the columns are NULL when the tag is missing
+CREATE TABLE photo_metadata
+(
+    photo_id      INTEGER NOT NULL PRIMARY KEY,
+    camera_make   TEXT,
+    camera_model  TEXT,
+    lens_model    TEXT,
+    -- In seconds
+    exposure_time REAL,
+    f_number      REAL,
+    iso           INTEGER,
+    -- In millimeters
+    focal_length  REAL,
+    width         INTEGER,
+    height        INTEGER,
+    orientation   INTEGER,
+    latitude      REAL,
+    longitude     REAL,
+    -- In meters above the sea level
+    altitude      REAL,
+
+    FOREIGN KEY (photo_id) REFERENCES photos (id) ON DELETE CASCADE
+);
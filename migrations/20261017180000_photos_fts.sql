@@ -0,0 +1,39 @@
+-- Full-text index of the photos, the rowid is the id of the photo.
+-- Caption and tags are empty until photos can have them
+CREATE VIRTUAL TABLE photos_fts USING fts5
+(
+    name,
+    folder,
+    caption,
+    tags,
+    tokenize = 'unicode61 remove_diacritics 2'
+);
+
+INSERT INTO photos_fts (rowid, name, folder, caption, tags)
+SELECT id, name, coalesce(folder, ''), '', ''
+FROM photos;
+
+CREATE TRIGGER photos_fts_insert
+    AFTER INSERT
+    ON photos
+BEGIN
+    INSERT INTO photos_fts (rowid, name, folder, caption, tags)
+    VALUES (new.id, new.name, coalesce(new.folder, ''), '', '');
+END;
+
+CREATE TRIGGER photos_fts_update
+    AFTER UPDATE OF name, folder
+    ON photos
+BEGIN
+    UPDATE photos_fts
+    SET name   = new.name,
+        folder = coalesce(new.folder, '')
+    WHERE rowid = new.id;
+END;
+
+CREATE TRIGGER photos_fts_delete
+    AFTER DELETE
+    ON photos
+BEGIN
+    DELETE FROM photos_fts WHERE rowid = old.id;
+END;
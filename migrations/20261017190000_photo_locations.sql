@@ -0,0 +1,2 @@
+-- Used to find the geotagged photos inside the area shown on the map
+CREATE INDEX photo_metadata_location_index ON photo_metadata (latitude, longitude) WHERE latitude IS NOT NULL;
@@ -0,0 +1,6 @@
+-- Place of the photo found from its GPS coordinates with the GeoNames dump, NULL when unknown
+ALTER TABLE photo_metadata ADD COLUMN city TEXT;
+ALTER TABLE photo_metadata ADD COLUMN region TEXT;
+ALTER TABLE photo_metadata ADD COLUMN country TEXT;
+
+CREATE INDEX photo_metadata_place_index ON photo_metadata (country, region, city) WHERE country IS NOT NULL;
@@ -0,0 +1,73 @@
+-- Free text describing a photo, edited by the users or imported from Google Takeout
+CREATE TABLE photo_captions
+(
+    photo_id INTEGER NOT NULL PRIMARY KEY,
+    caption  TEXT    NOT NULL,
+
+    FOREIGN KEY (photo_id) REFERENCES photos (id) ON DELETE CASCADE
+);
+
+-- Tags are shared by all the users, their names are case insensitive
+CREATE TABLE tags
+(
+    id   INTEGER NOT NULL PRIMARY KEY,
+    name TEXT    NOT NULL UNIQUE COLLATE NOCASE
+);
+
+CREATE TABLE photo_tags
+(
+    photo_id INTEGER NOT NULL,
+    tag_id   INTEGER NOT NULL,
+    PRIMARY KEY (photo_id, tag_id),
+
+    FOREIGN KEY (photo_id) REFERENCES photos (id) ON DELETE CASCADE,
+    FOREIGN KEY (tag_id) REFERENCES tags (id) ON DELETE CASCADE
+);
+
+CREATE INDEX photo_tags_tag_id_index ON photo_tags (tag_id);
+
+-- Keep the full-text index of the photos in sync with their caption and tags
+CREATE TRIGGER photo_captions_fts_insert
+    AFTER INSERT
+    ON photo_captions
+BEGIN
+    UPDATE photos_fts SET caption = new.caption WHERE rowid = new.photo_id;
+END;
+
+CREATE TRIGGER photo_captions_fts_update
+    AFTER UPDATE OF caption
+    ON photo_captions
+BEGIN
+    UPDATE photos_fts SET caption = new.caption WHERE rowid = new.photo_id;
+END;
+
+CREATE TRIGGER photo_captions_fts_delete
+    AFTER DELETE
+    ON photo_captions
+BEGIN
+    UPDATE photos_fts SET caption = '' WHERE rowid = old.photo_id;
+END;
+
+CREATE TRIGGER photo_tags_fts_insert
+    AFTER INSERT
+    ON photo_tags
+BEGIN
+    UPDATE photos_fts
+    SET tags = (SELECT group_concat(tags.name, ' ')
+                FROM photo_tags
+                         JOIN tags ON tags.id = photo_tags.tag_id
+                WHERE photo_tags.photo_id = new.photo_id)
+    WHERE rowid = new.photo_id;
+END;
+
+CREATE TRIGGER photo_tags_fts_delete
+    AFTER DELETE
+    ON photo_tags
+BEGIN
+    UPDATE photos_fts
+    SET tags = coalesce((SELECT group_concat(tags.name, ' ')
+                         FROM photo_tags
+                                  JOIN tags ON tags.id = photo_tags.tag_id
+                         WHERE photo_tags.photo_id = old.photo_id), '')
+    WHERE rowid = old.photo_id;
+END;
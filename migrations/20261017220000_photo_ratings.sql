@@ -0,0 +1,15 @@
+-- Star ratings and color labels given by each user to the photos, like in Lightroom
+CREATE TABLE photo_ratings
+(
+    photo_id    INTEGER NOT NULL,
+    user_id     TEXT    NOT NULL,
+    -- 0 means unrated
+    rating      INTEGER NOT NULL DEFAULT 0 CHECK (rating BETWEEN 0 AND 5),
+    color_label TEXT CHECK (color_label IN ('red', 'yellow', 'green', 'blue', 'purple')),
+    PRIMARY KEY (photo_id, user_id),
+
+    FOREIGN KEY (photo_id) REFERENCES photos (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX photo_ratings_user_id_index ON photo_ratings (user_id);
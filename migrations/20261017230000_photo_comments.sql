@@ -0,0 +1,17 @@
+-- Comments left by the users on the photos they have access to
+CREATE TABLE photo_comments
+(
+    id         INTEGER  NOT NULL PRIMARY KEY,
+    photo_id   INTEGER  NOT NULL,
+    -- The author of the comment, the only one who can edit or delete it
+    user_id    TEXT     NOT NULL,
+    text       TEXT     NOT NULL,
+    created_at DATETIME NOT NULL,
+    -- Set when the comment was edited after being posted
+    edited_at  DATETIME,
+
+    FOREIGN KEY (photo_id) REFERENCES photos (id) ON DELETE CASCADE,
+    FOREIGN KEY (user_id) REFERENCES users (id) ON DELETE CASCADE
+);
+
+CREATE INDEX photo_comments_photo_id_index ON photo_comments (photo_id, id);
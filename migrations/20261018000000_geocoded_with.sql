@@ -0,0 +1,3 @@
+-- Version of the GeoNames dump the location of the photo was last looked up in, so that the
+-- locations without a close city are only looked up again when the dump changes
+ALTER TABLE photo_metadata ADD COLUMN geocoded_with TEXT;
@@ -0,0 +1,45 @@
+-- A shared folder also shares its subfolders
+DROP VIEW shared_photos;
+
+CREATE VIEW shared_photos AS
+SELECT photos.id AS photo_id, photo_shares.user_id, photo_shares.permission
+FROM photo_shares
+         JOIN photos ON photos.id = photo_shares.photo_id AND photos.user_id = photo_shares.owner_id
+UNION ALL
+SELECT photos.id AS photo_id, photo_shares.user_id, photo_shares.permission
+FROM photo_shares
+         JOIN photos ON photos.user_id = photo_shares.owner_id AND (
+            photos.folder = photo_shares.folder
+            OR substr(photos.folder, 1, length(photo_shares.folder) + 1) = photo_shares.folder || '/'
+        );
+
+DROP TRIGGER photo_shares_insert_change;
+DROP TRIGGER photo_shares_delete_change;
+
+CREATE TRIGGER photo_shares_insert_change
+    AFTER INSERT
+    ON photo_shares
+BEGIN
+    INSERT INTO photo_changes (photo_id, user_id, kind)
+    SELECT id, new.user_id, 'upsert'
+    FROM photos
+    WHERE user_id = new.owner_id AND (
+        id = new.photo_id
+        OR folder = new.folder
+        OR substr(folder, 1, length(new.folder) + 1) = new.folder || '/'
+    );
+END;
+
+CREATE TRIGGER photo_shares_delete_change
+    AFTER DELETE
+    ON photo_shares
+BEGIN
+    INSERT INTO photo_changes (photo_id, user_id, kind)
+    SELECT id, old.user_id, 'delete'
+    FROM photos
+    WHERE user_id = old.owner_id AND (
+        id = old.photo_id
+        OR folder = old.folder
+        OR substr(folder, 1, length(old.folder) + 1) = old.folder || '/'
+    );
+END;
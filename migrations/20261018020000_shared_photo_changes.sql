@@ -0,0 +1,49 @@
+-- Changes to shared photos are also journaled for the users they are shared with, so that syncing never has to look
+-- at the journal of another user. Whether the photo is still visible is resolved when syncing.
+CREATE TRIGGER photos_insert_shared_change
+    AFTER INSERT
+    ON photos
+BEGIN
+    INSERT INTO photo_changes (photo_id, user_id, kind)
+    SELECT new.id, user_id, 'upsert'
+    FROM photo_shares
+    WHERE owner_id = new.user_id AND (
+        photo_id = new.id
+        OR folder = new.folder
+        OR substr(new.folder, 1, length(folder) + 1) = folder || '/'
+    );
+END;
+
+-- Both the users who could see the photo before and the ones who can see it now are notified
+CREATE TRIGGER photos_update_shared_change
+    AFTER UPDATE
+    ON photos
+BEGIN
+    INSERT INTO photo_changes (photo_id, user_id, kind)
+    SELECT DISTINCT new.id, user_id, 'upsert'
+    FROM photo_shares
+    WHERE (owner_id = old.user_id AND (
+        photo_id = old.id
+        OR folder = old.folder
+        OR substr(old.folder, 1, length(folder) + 1) = folder || '/'
+    )) OR (owner_id = new.user_id AND (
+        photo_id = new.id
+        OR folder = new.folder
+        OR substr(new.folder, 1, length(folder) + 1) = folder || '/'
+    ));
+END;
+
+-- Runs before the delete since the shares of the photo are deleted along with it
+CREATE TRIGGER photos_delete_shared_change
+    BEFORE DELETE
+    ON photos
+BEGIN
+    INSERT INTO photo_changes (photo_id, user_id, kind)
+    SELECT old.id, user_id, 'delete'
+    FROM photo_shares
+    WHERE owner_id = old.user_id AND (
+        photo_id = old.id
+        OR folder = old.folder
+        OR substr(old.folder, 1, length(folder) + 1) = folder || '/'
+    );
+END;
@@ -0,0 +1,4 @@
+-- Time of the last PATCH of an upload, abandoned uploads expire some time after it
+ALTER TABLE uploads ADD COLUMN updated_at DATETIME NOT NULL DEFAULT '1970-01-01T00:00:00Z';
+
+UPDATE uploads SET updated_at = started_at;
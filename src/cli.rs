@@ -1,8 +1,13 @@
+use crate::file_scan::GooglePhotoJsonData;
 use crate::http::AppState;
+use crate::model::photo::PhotoBase;
 use crate::model::user::User;
 use crate::utils::password_hash::{generate_hash_from_password, generate_random_password};
+use crate::utils::{photo_metadata, xmp};
 use crate::{file_scan, previews};
 use clap::{Parser, Subcommand};
+use rayon::prelude::*;
+use tokio::task;
 
 #[derive(Parser)]
 #[command(author, version, about, long_about = None)]
@@ -50,6 +55,14 @@ enum PhotosCommand {
     ScanPhotos,
     /// Trigger a manual generation of previews
     GeneratePreviews,
+    /// Store the EXIF metadata of the photos added before it was saved in the database
+    ExtractMetadata,
+    /// Import the descriptions of Google Takeout json files as the captions of the photos
+    /// without one, ex: they were added before captions were imported
+    ImportCaptions,
+    /// Import the XMP star ratings of the photos their owner didn't rate yet,
+    /// ex: they were added before ratings were read from XMP
+    ImportRatings,
 }
 
 #[derive(Subcommand)]
@@ -155,5 +168,119 @@ async fn photos_commands(state: &AppState, command: PhotosCommand) {
             Ok(_) => println!("Preview generation finished"),
             Err(e) => eprintln!("Preview generation failed: {e}"),
         },
+        PhotosCommand::ExtractMetadata => match extract_missing_metadata(state).await {
+            Ok(count) => println!("Extracted the metadata of {count} photos"),
+            Err(e) => eprintln!("Metadata extraction failed: {e}"),
+        },
+        PhotosCommand::ImportCaptions => match import_missing_captions(state).await {
+            Ok(count) => println!("Imported the caption of {count} photos"),
+            Err(e) => eprintln!("Caption import failed: {e}"),
+        },
+        PhotosCommand::ImportRatings => match import_missing_ratings(state).await {
+            Ok(count) => println!("Imported the rating of {count} photos"),
+            Err(e) => eprintln!("Rating import failed: {e}"),
+        },
     }
 }
+
+async fn extract_missing_metadata(state: &AppState) -> Result<usize, String> {
+    let photos = state
+        .photos_repo
+        .get_photos_without_metadata()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let storage = state.storage.clone();
+    let geocoder = state.geocoder.clone();
+    let metadata = task::spawn_blocking(move || {
+        photos
+            .par_iter()
+            .map(|photo| {
+                let path = if photo.is_trashed() {
+                    storage.resolve_trash(photo.partial_trash_path())
+                } else {
+                    storage.resolve_photo(photo.partial_path())
+                };
+                let mut metadata = photo_metadata::read_metadata(path);
+                geocoder.assign_place(&mut metadata);
+                (photo.id, metadata)
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for chunk in metadata.chunks(512) {
+        state
+            .photos_repo
+            .insert_metadata(chunk)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(metadata.len())
+}
+
+async fn import_missing_captions(state: &AppState) -> Result<usize, String> {
+    let photos = state
+        .photos_repo
+        .get_photos_without_caption()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let storage = state.storage.clone();
+    let captions = task::spawn_blocking(move || {
+        photos
+            .par_iter()
+            .filter_map(|photo| {
+                GooglePhotoJsonData::read(storage.resolve_photo(photo.partial_path()))
+                    .as_ref()
+                    .and_then(GooglePhotoJsonData::description)
+                    .map(|description| (photo.id, description.to_string()))
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for chunk in captions.chunks(512) {
+        state
+            .photos_repo
+            .insert_captions(chunk)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(captions.len())
+}
+
+async fn import_missing_ratings(state: &AppState) -> Result<usize, String> {
+    let photos = state
+        .ratings_repo
+        .get_photos_without_owner_rating()
+        .await
+        .map_err(|e| e.to_string())?;
+
+    let storage = state.storage.clone();
+    let ratings = task::spawn_blocking(move || {
+        photos
+            .par_iter()
+            .filter_map(|photo| {
+                xmp::read_rating(storage.resolve_photo(photo.partial_path()))
+                    .map(|rating| (photo.id, photo.user_id.clone(), rating))
+            })
+            .collect::<Vec<_>>()
+    })
+    .await
+    .map_err(|e| e.to_string())?;
+
+    for chunk in ratings.chunks(512) {
+        state
+            .ratings_repo
+            .insert_owner_ratings(chunk)
+            .await
+            .map_err(|e| e.to_string())?;
+    }
+
+    Ok(ratings.len())
+}
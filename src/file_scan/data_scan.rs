@@ -1,5 +1,6 @@
 use std::ffi::OsStr;
 use std::fs;
+use std::path::Path;
 use std::time::Instant;
 
 use rayon::prelude::*;
@@ -9,7 +10,9 @@ use tracing::{debug, error, info, warn};
 use walkdir::{DirEntry, WalkDir};
 
 use crate::file_scan::timestamp;
+use crate::file_scan::timestamp::GooglePhotoJsonData;
 use crate::model::photo::{Photo, PhotoBase, PhotoBody};
+use crate::utils::{content_hash, file_name, photo_metadata, xmp};
 use crate::{AppState, StorageResolver, User};
 
 pub struct DataScan {
@@ -58,15 +61,18 @@ impl DataScan {
             fs::create_dir(user_path).unwrap()
         } else {
             let json_extension = Some(OsStr::new("json"));
-            let walk_dir = WalkDir::new(user_path).max_depth(2);
+            // Hidden folders are reserved for the server and never hold the user's photos
+            let walk_dir = WalkDir::new(&user_path)
+                .into_iter()
+                .filter_entry(|entry| entry.depth() == 0 || !is_hidden(entry));
 
-            for entry in walk_dir.into_iter().filter_map(|e| e.ok()) {
+            for entry in walk_dir.filter_map(|e| e.ok()) {
                 let path = entry.path();
-                if path.is_dir() || path.extension() == json_extension {
+                if path.is_dir() || path.extension() == json_extension || xmp::is_sidecar(path) {
                     continue;
                 }
 
-                if let Some(photo) = Self::parse_image(user.id.clone(), entry) {
+                if let Some(photo) = Self::parse_image(user.id.clone(), &user_path, entry) {
                     photos.push(photo)
                 }
             }
@@ -77,21 +83,30 @@ impl DataScan {
         (user, photos)
     }
 
-    pub fn parse_image(user_name: String, entry: DirEntry) -> Option<PhotoBody> {
+    pub fn parse_image(user_name: String, user_path: &Path, entry: DirEntry) -> Option<PhotoBody> {
         let path = entry.path();
 
-        if let Some(timestamp) = timestamp::get_timestamp_for_path(path) {
-            Some(PhotoBody::new(
+        let exif = photo_metadata::read_exif(path);
+        let json = GooglePhotoJsonData::read(path);
+
+        if let Some(timestamp) =
+            timestamp::get_timestamp_for_path(path, exif.as_ref(), json.as_ref())
+        {
+            let mut photo = PhotoBody::new(
                 user_name,
                 entry.file_name().to_string_lossy().to_string(),
                 timestamp,
                 fs::metadata(path).map_or(0i64, |data| data.len() as i64),
-                if entry.depth() == 2 {
-                    Some(path.parent()?.file_name()?.to_string_lossy().to_string())
-                } else {
-                    None
-                },
-            ))
+                file_name::relative_folder(user_path, path.parent()?),
+            );
+            photo.set_metadata(
+                exif.map(|exif| photo_metadata::extract_metadata(&exif))
+                    .unwrap_or_default(),
+            );
+            if let Some(description) = json.as_ref().and_then(GooglePhotoJsonData::description) {
+                photo.set_caption(description.to_string());
+            }
+            Some(photo)
         } else {
             warn!("No timestamp: {}", path.display());
             None
@@ -118,6 +133,22 @@ impl DataScan {
             // Keep only new photos
             found_photos.retain(|photo| !existing_photos_names.contains(&photo.full_name()));
 
+            // Only the new photos are hashed and searched for XMP, as that requires reading them
+            found_photos.par_iter_mut().for_each(|photo| {
+                let path = storage.resolve_photo(photo.partial_path());
+                match content_hash::hash_file(&path) {
+                    Ok(hash) => photo.set_content_hash(hash),
+                    Err(e) => warn!("Failed hashing {}: {e}", photo.partial_path()),
+                }
+                if let Some(rating) = xmp::read_rating(&path) {
+                    photo.set_rating(rating);
+                }
+
+                if let Some(metadata) = photo.metadata_mut() {
+                    app_state.geocoder.assign_place(metadata);
+                }
+            });
+
             if !found_photos.is_empty() {
                 info!(
                     "Adding {} new photos to user {}",
@@ -132,6 +163,31 @@ impl DataScan {
                 }
             }
 
+            // Photos added before content hashing existed
+            let missing_hashes = existing_photos
+                .par_iter()
+                .filter(|photo| photo.content_hash.is_none())
+                .filter_map(|photo| {
+                    content_hash::hash_file(storage.resolve_photo(photo.partial_path()))
+                        .ok()
+                        .map(|hash| (photo.id(), hash))
+                })
+                .collect::<Vec<_>>();
+
+            if !missing_hashes.is_empty() {
+                info!(
+                    "Computed the hashes of {} photos from user {}",
+                    missing_hashes.len(),
+                    user.id
+                );
+
+                for chunk in missing_hashes.chunks(512) {
+                    if let Err(e) = photos_repo.update_content_hashes(chunk).await {
+                        error!("Failed updating photo hashes: {}", e.to_string())
+                    }
+                }
+            }
+
             let removed_photos = existing_photos
                 .iter()
                 .filter(|photo| !storage.resolve_photo(photo.partial_path()).exists())
@@ -150,5 +206,50 @@ impl DataScan {
                 }
             }
         }
+
+        Self::locate_photos(app_state).await;
+    }
+
+    /// Finds the place of the geotagged photos that don't have one yet,
+    /// ex: they were added before a GeoNames dump was configured.
+    /// Photos without a city close enough are only looked up again with another dump.
+    async fn locate_photos(app_state: &AppState) {
+        let geocoder = &app_state.geocoder;
+        let Some(version) = geocoder.version() else {
+            return;
+        };
+
+        let locations = match app_state.photos_repo.get_unplaced_locations(version).await {
+            Ok(locations) => locations,
+            Err(e) => {
+                error!("Failed getting photo locations: {e}");
+                return;
+            }
+        };
+
+        let places = locations
+            .par_iter()
+            .map(|location| {
+                (
+                    location.photo_id,
+                    geocoder.locate(location.latitude, location.longitude),
+                )
+            })
+            .collect::<Vec<_>>();
+
+        let found = places.iter().filter(|(_, place)| place.is_some()).count();
+        if found != 0 {
+            info!("Found the place of {found} photos");
+        }
+
+        for chunk in places.chunks(512) {
+            if let Err(e) = app_state.photos_repo.update_places(chunk, version).await {
+                error!("Failed updating photo places: {e}")
+            }
+        }
     }
 }
+
+fn is_hidden(entry: &DirEntry) -> bool {
+    entry.file_name().to_string_lossy().starts_with('.')
+}
@@ -6,6 +6,8 @@ use tracing::debug;
 mod data_scan;
 mod timestamp;
 
+pub use timestamp::GooglePhotoJsonData;
+
 pub fn scan_new_files(app_state: AppState) -> JoinHandle<()> {
     debug!("Started scanning for new files");
     DataScan::run(app_state)
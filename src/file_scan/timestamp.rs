@@ -1,5 +1,4 @@
-use exif::{Field, In, Tag, Value};
-use mime_guess::MimeGuess;
+use exif::{Exif, Field, In, Tag, Value};
 use regex::Regex;
 use serde::Deserialize;
 use std::fs;
@@ -11,10 +10,18 @@ use time::macros::format_description;
 use time::{OffsetDateTime, PrimitiveDateTime};
 use tracing::error;
 
-pub fn get_timestamp_for_path<P: AsRef<Path>>(path: P) -> Option<OffsetDateTime> {
-    get_json_timestamp(path.as_ref())
+///
+/// The `exif` and Google Takeout `json` of the file are taken already parsed,
+/// as they are also needed for its metadata and caption
+///
+pub fn get_timestamp_for_path<P: AsRef<Path>>(
+    path: P,
+    exif: Option<&Exif>,
+    json: Option<&GooglePhotoJsonData>,
+) -> Option<OffsetDateTime> {
+    json.and_then(GooglePhotoJsonData::timestamp)
         .map_or_else(
-            || get_exif_timestamp(path.as_ref()),
+            || exif.and_then(get_exif_timestamp),
             |json_timestamp| OffsetDateTime::from_unix_timestamp(json_timestamp as i64).ok(),
         )
         .or_else(|| get_regex_timestamp(path))
@@ -26,14 +33,40 @@ struct GooglePhotoTimestamp {
     timestamp: String,
 }
 
+/// The `<photo>.json` file exported by Google Takeout next to each photo
 #[derive(Deserialize)]
 #[serde(rename_all = "camelCase")]
-struct GooglePhotoJsonData {
+pub struct GooglePhotoJsonData {
     creation_time: Option<GooglePhotoTimestamp>,
     photo_taken_time: Option<GooglePhotoTimestamp>,
+    /// The caption of the photo, empty when it has none
+    description: Option<String>,
 }
 
 impl GooglePhotoJsonData {
+    /// Reads the Google Takeout metadata of the file, if it has any
+    pub fn read<P: AsRef<Path>>(path: P) -> Option<Self> {
+        let json_file_name = format!("{}.json", path.as_ref().to_string_lossy());
+
+        let file = fs::File::open(&json_file_name).ok()?;
+        let reader = BufReader::new(file);
+
+        serde_json::from_reader(reader)
+            .inspect_err(|e| error!("Failed parsing Json ({json_file_name}): {e}"))
+            .ok()
+    }
+
+    pub fn description(&self) -> Option<&str> {
+        self.description
+            .as_deref()
+            .map(str::trim)
+            .filter(|description| !description.is_empty())
+    }
+
+    fn timestamp(&self) -> Option<u64> {
+        self.u64_photo_taken_time().or(self.u64_creation_time())
+    }
+
     fn u64_creation_time(&self) -> Option<u64> {
         let time = self.creation_time.as_ref()?;
         time.timestamp.parse().ok()
@@ -45,24 +78,6 @@ impl GooglePhotoJsonData {
     }
 }
 
-fn get_json_timestamp(path: &Path) -> Option<u64> {
-    let json_file_name = format!("{}.json", path.to_string_lossy());
-
-    let file = fs::File::open(&json_file_name).ok()?;
-    let reader = BufReader::new(file);
-    let json = serde_json::from_reader::<_, GooglePhotoJsonData>(reader);
-
-    match json {
-        Ok(json_data) => json_data
-            .u64_photo_taken_time()
-            .or(json_data.u64_creation_time()),
-        Err(e) => {
-            error!("Failed parsing Json ({json_file_name}): {e}");
-            None
-        }
-    }
-}
-
 fn is_datetime(f: &Field, tag: Tag) -> Option<OffsetDateTime> {
     let format = format_description!("[year]:[month]:[day] [hour]:[minute]:[second]");
 
@@ -75,32 +90,24 @@ fn is_datetime(f: &Field, tag: Tag) -> Option<OffsetDateTime> {
                         return None;
                     }
                 }
-                return from_utf8(&v[0]).ok();
+                from_utf8(&v[0]).ok()
             }
             _ => None,
         }
     }
 
     if f.tag == tag {
-        single_ascii(&f.value).and_then(|s| OffsetDateTime::parse(s, &format).ok())
+        // EXIF dates have no offset
+        single_ascii(&f.value)
+            .and_then(|s| PrimitiveDateTime::parse(s, &format).ok())
+            .map(PrimitiveDateTime::assume_utc)
     } else {
         None
     }
 }
 
-fn get_exif_timestamp(path: &Path) -> Option<OffsetDateTime> {
-    let mime = MimeGuess::from_ext(path.extension()?.to_str()?).first_or_octet_stream();
-    if mime.type_() != "image" {
-        return None;
-    }
-
-    let file = fs::File::open(path).ok()?;
-    let mut bufreader = BufReader::new(&file);
-    let reader = exif::Reader::new()
-        .read_from_container(&mut bufreader)
-        .ok()?;
-
-    for f in reader.fields() {
+fn get_exif_timestamp(exif: &Exif) -> Option<OffsetDateTime> {
+    for f in exif.fields() {
         if f.ifd_num == In::PRIMARY {
             if let Some(d) = is_datetime(f, Tag::DateTimeOriginal) {
                 return Some(d);
@@ -173,6 +180,21 @@ mod tests {
 
     use super::*;
 
+    #[test]
+    fn test_exif_datetime() {
+        let field = Field {
+            tag: Tag::DateTimeOriginal,
+            ifd_num: In::PRIMARY,
+            value: Value::Ascii(vec![b"2016:09:22 16:04:30".to_vec()]),
+        };
+
+        assert_eq!(
+            is_datetime(&field, Tag::DateTimeOriginal),
+            Some(datetime!(2016-09-22 16:04:30 UTC))
+        );
+        assert_eq!(is_datetime(&field, Tag::DateTime), None);
+    }
+
     #[test]
     fn test_regex_timestamp() {
         let expected_date = Some(datetime!(2016-09-22 16:04:30 UTC));
@@ -225,5 +247,10 @@ mod tests {
         let data: GooglePhotoJsonData = serde_json::from_str(json).unwrap();
         assert_eq!(data.u64_creation_time(), Some(1437327811));
         assert_eq!(data.u64_photo_taken_time(), Some(1435786122));
+        assert_eq!(data.description(), None);
+
+        let data: GooglePhotoJsonData =
+            serde_json::from_str(r#"{ "description": " Sunset at the beach " }"#).unwrap();
+        assert_eq!(data.description(), Some("Sunset at the beach"));
     }
 }
@@ -0,0 +1,285 @@
+use std::collections::HashMap;
+use std::fs::File;
+use std::io::{self, BufRead, BufReader};
+use std::path::Path;
+use std::sync::Arc;
+
+use tracing::info;
+
+use crate::model::photo_metadata::PhotoMetadata;
+use crate::model::place::Place;
+
+/// Cities further away than this from a photo are not considered to be its place
+const MAX_DISTANCE_KM: f64 = 100.0;
+const EARTH_RADIUS_KM: f64 = 6371.0;
+
+struct City {
+    name: String,
+    latitude: f64,
+    longitude: f64,
+    country_code: String,
+    /// `<country code>.<admin1 code>`, as used by `admin1CodesASCII.txt`
+    region_code: String,
+}
+
+struct Index {
+    cities: Vec<City>,
+    /// Indices of the cities in each 1° by 1° cell, keyed by the floored latitude and longitude
+    grid: HashMap<(i32, i32), Vec<usize>>,
+    regions: HashMap<String, String>,
+    countries: HashMap<String, String>,
+    /// CRC-32 of the cities dump
+    version: String,
+}
+
+///
+/// Finds the closest city to a location using a GeoNames dump, without any online service.
+/// An empty geocoder, used when no dump is configured, never finds a place.
+///
+#[derive(Clone, Default)]
+pub struct ReverseGeocoder {
+    index: Option<Arc<Index>>,
+}
+
+impl ReverseGeocoder {
+    ///
+    /// Loads a GeoNames cities dump like `cities1000.txt`. The region and country names are read
+    /// from `admin1CodesASCII.txt` and `countryInfo.txt` in the same folder, if they exist,
+    /// otherwise the regions are left out and the countries are named by their code.
+    ///
+    pub fn load(path: &Path) -> io::Result<Self> {
+        let folder = path.parent().unwrap_or(Path::new("."));
+        let open_optional = |name: &str| -> io::Result<Option<BufReader<File>>> {
+            match File::open(folder.join(name)) {
+                Ok(file) => Ok(Some(BufReader::new(file))),
+                Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+                Err(e) => Err(e),
+            }
+        };
+
+        let geocoder = Self::from_readers(
+            BufReader::new(File::open(path)?),
+            open_optional("admin1CodesASCII.txt")?,
+            open_optional("countryInfo.txt")?,
+        )?;
+
+        info!(
+            "Loaded {} cities for reverse geocoding",
+            geocoder
+                .index
+                .as_ref()
+                .map_or(0, |index| index.cities.len())
+        );
+        Ok(geocoder)
+    }
+
+    fn from_readers(
+        cities: impl BufRead,
+        regions: Option<impl BufRead>,
+        countries: Option<impl BufRead>,
+    ) -> io::Result<Self> {
+        let mut index = Index {
+            cities: Vec::new(),
+            grid: HashMap::new(),
+            regions: HashMap::new(),
+            countries: HashMap::new(),
+            version: String::new(),
+        };
+
+        let mut hasher = crc32fast::Hasher::new();
+        for line in cities.lines() {
+            let line = line?;
+            hasher.update(line.as_bytes());
+            let fields = line.split('\t').collect::<Vec<_>>();
+            if fields.len() < 11 {
+                continue;
+            }
+
+            let (Ok(latitude), Ok(longitude)) = (fields[4].parse(), fields[5].parse()) else {
+                continue;
+            };
+
+            index
+                .grid
+                .entry(cell(latitude, longitude))
+                .or_default()
+                .push(index.cities.len());
+            index.cities.push(City {
+                name: fields[1].to_string(),
+                latitude,
+                longitude,
+                country_code: fields[8].to_string(),
+                region_code: format!("{}.{}", fields[8], fields[10]),
+            });
+        }
+
+        index.version = format!("{:08x}", hasher.finalize());
+
+        // Lines of `<country code>.<admin1 code>  <name>  <ascii name>  <geoname id>`
+        for line in regions.into_iter().flat_map(|regions| regions.lines()) {
+            let line = line?;
+            if let Some((code, rest)) = line.split_once('\t') {
+                let name = rest.split('\t').next().unwrap_or_default();
+                index.regions.insert(code.to_string(), name.to_string());
+            }
+        }
+
+        // The country name is the 5th column, comments start with '#'
+        for line in countries
+            .into_iter()
+            .flat_map(|countries| countries.lines())
+        {
+            let line = line?;
+            if line.starts_with('#') {
+                continue;
+            }
+            let fields = line.split('\t').collect::<Vec<_>>();
+            if fields.len() > 4 {
+                index
+                    .countries
+                    .insert(fields[0].to_string(), fields[4].to_string());
+            }
+        }
+
+        Ok(Self {
+            index: Some(Arc::new(index)),
+        })
+    }
+
+    /// Identifies the loaded dump, which changes whenever the cities in it change
+    pub fn version(&self) -> Option<&str> {
+        self.index.as_ref().map(|index| index.version.as_str())
+    }
+
+    /// Returns the place of the closest city, if there is one close enough
+    pub fn locate(&self, latitude: f64, longitude: f64) -> Option<Place> {
+        let index = self.index.as_ref()?;
+        let (cell_latitude, cell_longitude) = cell(latitude, longitude);
+
+        let mut closest: Option<(&City, f64)> = None;
+        for latitude_offset in -1..=1 {
+            for longitude_offset in -1..=1 {
+                let key = (
+                    cell_latitude + latitude_offset,
+                    wrap_longitude_cell(cell_longitude + longitude_offset),
+                );
+
+                for &city_index in index.grid.get(&key).into_iter().flatten() {
+                    let city = &index.cities[city_index];
+                    let distance = distance_km(latitude, longitude, city.latitude, city.longitude);
+                    if distance <= MAX_DISTANCE_KM
+                        && closest.is_none_or(|(_, closest_distance)| distance < closest_distance)
+                    {
+                        closest = Some((city, distance));
+                    }
+                }
+            }
+        }
+
+        let (city, _) = closest?;
+        Some(Place {
+            city: city.name.clone(),
+            region: index.regions.get(&city.region_code).cloned(),
+            country: index
+                .countries
+                .get(&city.country_code)
+                .cloned()
+                .unwrap_or_else(|| city.country_code.clone()),
+        })
+    }
+
+    /// Sets the place of the metadata if it has GPS coordinates, marking them as looked up
+    /// in this version of the dump even when no city is close enough
+    pub fn assign_place(&self, metadata: &mut PhotoMetadata) {
+        let (Some(latitude), Some(longitude), Some(version)) =
+            (metadata.latitude, metadata.longitude, self.version())
+        else {
+            return;
+        };
+
+        if let Some(place) = self.locate(latitude, longitude) {
+            metadata.set_place(place);
+        }
+        metadata.geocoded_with = Some(version.to_string());
+    }
+}
+
+fn cell(latitude: f64, longitude: f64) -> (i32, i32) {
+    (latitude.floor() as i32, longitude.floor() as i32)
+}
+
+/// The cells of -180° and 179° are next to each other
+fn wrap_longitude_cell(longitude: i32) -> i32 {
+    (longitude + 180).rem_euclid(360) - 180
+}
+
+/// Great-circle distance using the haversine formula
+fn distance_km(latitude1: f64, longitude1: f64, latitude2: f64, longitude2: f64) -> f64 {
+    let d_latitude = (latitude2 - latitude1).to_radians();
+    let d_longitude = (longitude2 - longitude1).to_radians();
+
+    let a = (d_latitude / 2.0).sin().powi(2)
+        + latitude1.to_radians().cos()
+            * latitude2.to_radians().cos()
+            * (d_longitude / 2.0).sin().powi(2);
+
+    2.0 * EARTH_RADIUS_KM * a.sqrt().asin()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const CITIES: &str = "683844\tBrașov\tBrasov\t\t45.64861\t25.60613\tP\tPPLA\tRO\t\t05\t\t\t\t253200\t\t\tEurope/Bucharest\t2019-09-05
+685948\tRâșnov\tRasnov\t\t45.59167\t25.46667\tP\tPPL\tRO\t\t05\t\t\t\t15000\t\t\tEurope/Bucharest\t2019-09-05
+2193733\tAuckland\tAuckland\t\t-36.84853\t174.76349\tP\tPPLA\tNZ\t\t\t\t\t\t417910\t\t\tPacific/Auckland\t2019-09-05
+2196360\tWaiyevo\tWaiyevo\t\t-16.7864\t179.9895\tP\tPPL\tFJ\t\t\t\t\t\t600\t\t\tPacific/Fiji\t2019-09-05";
+    const REGIONS: &str = "RO.05\tJudețul Brașov\tJudetul Brasov\t683843";
+    const COUNTRIES: &str = "#ISO\tISO3\tISO-Numeric\tfips\tCountry
+RO\tROU\t642\tRO\tRomania";
+
+    fn geocoder() -> ReverseGeocoder {
+        ReverseGeocoder::from_readers(
+            CITIES.as_bytes(),
+            Some(REGIONS.as_bytes()),
+            Some(COUNTRIES.as_bytes()),
+        )
+        .unwrap()
+    }
+
+    #[test]
+    fn closest_city() {
+        let geocoder = geocoder();
+
+        assert_eq!(
+            geocoder.locate(45.65, 25.59),
+            Some(Place {
+                city: "Brașov".to_string(),
+                region: Some("Județul Brașov".to_string()),
+                country: "Romania".to_string(),
+            })
+        );
+        assert_eq!(
+            geocoder.locate(45.58, 25.45).map(|place| place.city),
+            Some("Râșnov".to_string())
+        );
+        // The country is named by its code when missing from countryInfo.txt
+        assert_eq!(
+            geocoder.locate(-36.85, 174.76),
+            Some(Place {
+                city: "Auckland".to_string(),
+                region: None,
+                country: "NZ".to_string(),
+            })
+        );
+        // Across the antimeridian
+        assert_eq!(
+            geocoder.locate(-16.79, -179.99).map(|place| place.city),
+            Some("Waiyevo".to_string())
+        );
+        // In the middle of the ocean
+        assert_eq!(geocoder.locate(0.0, -30.0), None);
+        assert_eq!(ReverseGeocoder::default().locate(45.65, 25.59), None);
+        assert_eq!(ReverseGeocoder::default().version(), None);
+    }
+}
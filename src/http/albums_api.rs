@@ -0,0 +1,243 @@
+use std::collections::HashSet;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{delete, get, put};
+use axum::{Json, Router};
+
+use crate::http::utils::status_error::StatusError;
+use crate::http::utils::{check_has_access, AuthSession, AxumResult};
+use crate::http::AppState;
+use crate::model::album::Album;
+use crate::model::photo_share::SharePermission;
+use crate::model::user::User;
+
+pub fn router(app_state: AppState) -> Router {
+    Router::new()
+        .route("/", get(list_albums).post(create_album))
+        .route(
+            "/{album_id}",
+            get(get_album).put(update_album).delete(delete_album),
+        )
+        .route(
+            "/{album_id}/photos",
+            get(album_photos).post(add_album_photos),
+        )
+        .route("/{album_id}/photos/{photo_id}", delete(remove_album_photo))
+        .route("/{album_id}/order", put(reorder_album_photos))
+        .with_state(app_state)
+}
+
+/// Fetches the album making sure it belongs to the current user
+async fn get_user_album(
+    state: &AppState,
+    auth: AuthSession,
+    album_id: i64,
+) -> AxumResult<(User, Album)> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let album = state
+        .albums_repo
+        .get_album(album_id)
+        .await?
+        .ok_or_else(|| StatusError::new_status("Album not found", StatusCode::NOT_FOUND))?;
+
+    if album.user_id != user.id {
+        return Err(StatusError::new_status(
+            "You don't have access to this resource",
+            StatusCode::FORBIDDEN,
+        ));
+    }
+
+    Ok((user, album))
+}
+
+fn validate_name(name: &str) -> AxumResult<String> {
+    let name = name.trim();
+    if name.is_empty() {
+        return Err(StatusError::new_status(
+            "Album name can't be empty",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    Ok(name.to_string())
+}
+
+async fn list_albums(
+    State(state): State<AppState>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    Ok(Json(state.albums_repo.get_albums(user.id).await?))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateAlbumBody {
+    name: String,
+    description: Option<String>,
+}
+
+async fn create_album(
+    State(state): State<AppState>,
+    auth: AuthSession,
+    Json(body): Json<CreateAlbumBody>,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+    let name = validate_name(&body.name)?;
+
+    let album = state
+        .albums_repo
+        .insert_album(user.id, name, body.description.as_deref())
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(album)))
+}
+
+async fn get_album(
+    State(state): State<AppState>,
+    Path(album_id): Path<i64>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let (_, album) = get_user_album(&state, auth, album_id).await?;
+
+    Ok(Json(album))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UpdateAlbumBody {
+    name: String,
+    description: Option<String>,
+    cover_photo_id: Option<i64>,
+}
+
+/// Replaces the name, description and cover of the album
+async fn update_album(
+    State(state): State<AppState>,
+    Path(album_id): Path<i64>,
+    auth: AuthSession,
+    Json(body): Json<UpdateAlbumBody>,
+) -> AxumResult<impl IntoResponse> {
+    let (_, album) = get_user_album(&state, auth, album_id).await?;
+
+    if let Some(cover_photo_id) = body.cover_photo_id
+        && !state
+            .albums_repo
+            .contains_photo(album.id, cover_photo_id)
+            .await?
+    {
+        return Err(StatusError::new_status(
+            "The cover must be a photo from the album",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let changed_album = Album {
+        name: validate_name(&body.name)?,
+        description: body.description,
+        cover_photo_id: body.cover_photo_id,
+        ..album
+    };
+    state.albums_repo.update_album(&changed_album).await?;
+
+    Ok(Json(changed_album))
+}
+
+async fn delete_album(
+    State(state): State<AppState>,
+    Path(album_id): Path<i64>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let (_, album) = get_user_album(&state, auth, album_id).await?;
+
+    state.albums_repo.delete_album(album.id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn album_photos(
+    State(state): State<AppState>,
+    Path(album_id): Path<i64>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let (user, album) = get_user_album(&state, auth, album_id).await?;
+
+    Ok(Json(
+        state
+            .albums_repo
+            .get_album_photos(album.id, user.id)
+            .await?,
+    ))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct AlbumPhotosBody {
+    photo_ids: Vec<i64>,
+}
+
+/// Appends the photos to the album, all of them must be accessible by the user
+async fn add_album_photos(
+    State(state): State<AppState>,
+    Path(album_id): Path<i64>,
+    auth: AuthSession,
+    Json(body): Json<AlbumPhotosBody>,
+) -> AxumResult<impl IntoResponse> {
+    let (user, album) = get_user_album(&state, auth, album_id).await?;
+
+    let photos = state.photos_repo.get_photos(&body.photo_ids).await?;
+    let found_ids = photos.iter().map(|photo| photo.id).collect::<HashSet<_>>();
+    if let Some(photo_id) = body
+        .photo_ids
+        .iter()
+        .find(|photo_id| !found_ids.contains(photo_id))
+    {
+        return Err(StatusError::new_status(
+            format!("Photo {photo_id} not found"),
+            StatusCode::NOT_FOUND,
+        ));
+    }
+
+    for photo in &photos {
+        check_has_access(&state, Some(user.clone()), photo, SharePermission::Read).await?;
+    }
+
+    state
+        .albums_repo
+        .add_photos(album.id, &body.photo_ids)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn remove_album_photo(
+    State(state): State<AppState>,
+    Path((album_id, photo_id)): Path<(i64, i64)>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let (_, album) = get_user_album(&state, auth, album_id).await?;
+
+    state.albums_repo.remove_photo(album.id, photo_id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn reorder_album_photos(
+    State(state): State<AppState>,
+    Path(album_id): Path<i64>,
+    auth: AuthSession,
+    Json(body): Json<AlbumPhotosBody>,
+) -> AxumResult<impl IntoResponse> {
+    let (_, album) = get_user_album(&state, auth, album_id).await?;
+
+    state
+        .albums_repo
+        .reorder_photos(album.id, &body.photo_ids)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
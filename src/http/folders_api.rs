@@ -0,0 +1,522 @@
+use std::collections::HashSet;
+use std::path::Path;
+
+use axum::extract::{Query, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use tokio::{fs, task};
+use tracing::{info, warn};
+
+use crate::http::utils::status_error::StatusError;
+use crate::http::utils::{check_folder_access, check_folder_name, AuthSession, AxumResult};
+use crate::http::AppState;
+use crate::model::folder::Folder;
+use crate::model::photo::{Photo, PhotoBase};
+use crate::model::photo_share::SharePermission;
+use crate::utils::{file_name, internal_error};
+
+/// How deep folders without photos are searched for inside another folder
+const MAX_EMPTY_FOLDER_DEPTH: usize = 3;
+
+pub fn router(app_state: AppState) -> Router {
+    Router::new()
+        .route("/", get(list_folders).delete(delete_folder))
+        .route("/rename", post(rename_folder))
+        .route("/merge", post(merge_folders))
+        .with_state(app_state)
+}
+
+/// The owner of the folder, the current user if none is given
+fn folder_owner(auth: &AuthSession, user_name: Option<String>) -> AxumResult<String> {
+    user_name
+        .or_else(|| auth.user.as_ref().map(|user| user.id.clone()))
+        .ok_or_else(|| StatusCode::UNAUTHORIZED.into())
+}
+
+fn folder_not_found() -> axum::response::ErrorResponse {
+    StatusError::new_status("Folder not found", StatusCode::NOT_FOUND)
+}
+
+/// The folder as seen by the user, empty if it has no visible photos
+async fn get_folder(
+    state: &AppState,
+    user_id: &str,
+    owner_id: &str,
+    folder: &str,
+) -> AxumResult<Folder> {
+    let folder = state
+        .folders_repo
+        .get_folders(user_id, Some(owner_id), Some(folder))
+        .await?
+        .pop()
+        .unwrap_or_else(|| Folder::empty(owner_id.to_string(), folder.to_string()));
+
+    Ok(folder)
+}
+
+/// Moves the file of a trashed photo so that it is restored into its new folder
+fn move_trashed_photo(state: &AppState, photo: &Photo, moved_photo: &Photo) {
+    if let Err(e) = state
+        .storage
+        .move_in_trash(photo.partial_trash_path(), moved_photo.partial_trash_path())
+    {
+        warn!("Failed moving trashed photo {}: {e}", photo.id);
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ListFoldersQuery {
+    /// Only the folders of this user, all the visible folders if missing
+    user_name: Option<String>,
+}
+
+///
+/// Lists the folders with photos visible to the user.
+/// The empty directories of the user are included as well, so that they can be deleted.
+///
+async fn list_folders(
+    State(state): State<AppState>,
+    Query(query): Query<ListFoldersQuery>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let mut folders = state
+        .folders_repo
+        .get_folders(&user.id, query.user_name.as_deref(), None)
+        .await?;
+
+    if query.user_name.as_ref().is_none_or(|name| name == &user.id) {
+        let existing = folders
+            .iter()
+            .filter(|folder| folder.user_id == user.id)
+            .map(|folder| folder.name.clone())
+            .collect::<HashSet<_>>();
+
+        let user_path = state.storage.resolve_photo(&user.id);
+        let known_folders = existing.clone();
+        let directories =
+            task::spawn_blocking(move || list_empty_directories(&user_path, &known_folders))
+                .await
+                .map_err(internal_error)?;
+
+        folders.extend(
+            directories
+                .into_iter()
+                .filter(|name| !existing.contains(name))
+                .map(|name| Folder::empty(user.id.clone(), name)),
+        );
+        folders.sort_by(|a, b| (&a.user_id, &a.name).cmp(&(&b.user_id, &b.name)));
+    }
+
+    Ok(Json(folders))
+}
+
+///
+/// The folders inside the user's folder without photos, except for the hidden ones.
+/// Rather than walking the whole storage, only the user's folder, the folders with photos and
+/// their parents are read, and the folders without photos are followed up to
+/// [MAX_EMPTY_FOLDER_DEPTH] levels deep.
+///
+fn list_empty_directories(user_path: &Path, known_folders: &HashSet<String>) -> Vec<String> {
+    let mut parents = HashSet::new();
+    for folder in known_folders {
+        let mut parent = folder.as_str();
+        while let Some((ancestor, _)) = parent.rsplit_once('/') {
+            parents.insert(ancestor.to_string());
+            parent = ancestor;
+        }
+    }
+
+    // The parents of folders with photos are folders without any photo of their own
+    let mut empty_folders = parents
+        .iter()
+        .filter(|parent| !known_folders.contains(*parent))
+        .cloned()
+        .collect::<Vec<_>>();
+
+    let mut pending = std::iter::once(user_path.to_path_buf())
+        .chain(
+            known_folders
+                .iter()
+                .chain(&parents)
+                .map(|folder| user_path.join(folder)),
+        )
+        .map(|path| (path, 0))
+        .collect::<Vec<_>>();
+
+    while let Some((path, depth)) = pending.pop() {
+        let Ok(entries) = std::fs::read_dir(&path) else {
+            continue;
+        };
+
+        for entry in entries.filter_map(|entry| entry.ok()) {
+            if !entry.file_type().is_ok_and(|file_type| file_type.is_dir())
+                || entry.file_name().to_string_lossy().starts_with('.')
+            {
+                continue;
+            }
+            let Some(folder) = file_name::relative_folder(user_path, &entry.path()) else {
+                continue;
+            };
+            if known_folders.contains(&folder) || parents.contains(&folder) {
+                continue;
+            }
+
+            if depth < MAX_EMPTY_FOLDER_DEPTH {
+                pending.push((entry.path(), depth + 1));
+            }
+            empty_folders.push(folder);
+        }
+    }
+
+    empty_folders
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RenameFolderBody {
+    user_name: Option<String>,
+    folder: String,
+    name: String,
+}
+
+///
+/// Renames a folder, moving its directory and updating its photos, shares and share links.
+/// The subfolders are moved along, so the new name can also move the folder under another one.
+/// Fails if a folder with the new name already exists, [merge_folders] is meant for that.
+///
+async fn rename_folder(
+    State(state): State<AppState>,
+    auth: AuthSession,
+    Json(body): Json<RenameFolderBody>,
+) -> AxumResult<impl IntoResponse> {
+    let storage = &state.storage;
+    let owner_id = folder_owner(&auth, body.user_name)?;
+    check_folder_name(&body.folder)?;
+    check_folder_name(&body.name)?;
+
+    let user = check_folder_access(
+        &state,
+        auth.user,
+        &owner_id,
+        &body.folder,
+        SharePermission::Contribute,
+    )
+    .await?;
+
+    let source_path = format!("{owner_id}/{}", body.folder);
+    let destination_path = format!("{owner_id}/{}", body.name);
+
+    let photos = state
+        .folders_repo
+        .get_folder_tree_photos(&owner_id, &body.folder)
+        .await?;
+    let has_directory = storage.resolve_photo(&source_path).is_dir();
+
+    if photos.is_empty() && !has_directory {
+        return Err(folder_not_found());
+    }
+    if body.folder == body.name {
+        return Ok(Json(
+            get_folder(&state, &user.id, &owner_id, &body.name).await?,
+        ));
+    }
+    if body.name.starts_with(&format!("{}/", body.folder)) {
+        return Err(StatusError::new_status(
+            "A folder can't be moved into one of its subfolders",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    if storage.resolve_photo(&destination_path).exists()
+        || !state
+            .folders_repo
+            .get_folder_tree_photos(&owner_id, &body.name)
+            .await?
+            .is_empty()
+    {
+        return Err(StatusError::new_status(
+            "A folder with this name already exists",
+            StatusCode::CONFLICT,
+        ));
+    }
+
+    info!("Renaming folder from {source_path} to {destination_path}");
+
+    if has_directory {
+        storage
+            .move_folder(&source_path, &destination_path)
+            .map_err(|e| match e.kind() {
+                std::io::ErrorKind::AlreadyExists => StatusError::new_status(
+                    "A folder with this name already exists",
+                    StatusCode::CONFLICT,
+                ),
+                _ => StatusError::create(format!("Failed renaming the folder: {e}")),
+            })?;
+    }
+
+    if let Err(e) = state
+        .folders_repo
+        .rename_folder(&owner_id, &body.folder, &body.name)
+        .await
+    {
+        // Put the directory back where the database expects it
+        if has_directory {
+            let _ = storage.move_folder(&destination_path, &source_path);
+        }
+        return Err(e);
+    }
+
+    for photo in photos.iter().filter(|photo| photo.is_trashed()) {
+        // Photos in subfolders keep their path inside the renamed folder
+        let subfolder =
+            photo.folder.as_deref().unwrap_or_default()[body.folder.len()..].to_string();
+        let moved_photo = Photo {
+            folder: Some(body.name.clone() + &subfolder),
+            ..photo.clone()
+        };
+        move_trashed_photo(&state, photo, &moved_photo);
+    }
+
+    Ok(Json(
+        get_folder(&state, &user.id, &owner_id, &body.name).await?,
+    ))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MergeFoldersBody {
+    user_name: Option<String>,
+    folder: String,
+    /// The folder receiving the photos, created if it doesn't exist
+    into: String,
+}
+
+///
+/// Moves all the photos of a folder into another folder of the same user and removes the
+/// merged folder along with its shares and share links.
+/// Photos whose name is taken in the target folder get a free name: "name (1).jpg", trashed
+/// photos included so that they can be restored next to the others.
+/// Folders with subfolders can't be merged, as their subfolders would be left behind.
+///
+async fn merge_folders(
+    State(state): State<AppState>,
+    auth: AuthSession,
+    Json(body): Json<MergeFoldersBody>,
+) -> AxumResult<impl IntoResponse> {
+    let storage = &state.storage;
+    let owner_id = folder_owner(&auth, body.user_name)?;
+    check_folder_name(&body.folder)?;
+    check_folder_name(&body.into)?;
+
+    if body.folder == body.into || body.into.starts_with(&format!("{}/", body.folder)) {
+        return Err(StatusError::new_status(
+            "A folder can't be merged into itself or one of its subfolders",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let user = check_folder_access(
+        &state,
+        auth.user,
+        &owner_id,
+        &body.folder,
+        SharePermission::Contribute,
+    )
+    .await?;
+    let user = check_folder_access(
+        &state,
+        Some(user),
+        &owner_id,
+        &body.into,
+        SharePermission::Contribute,
+    )
+    .await?;
+
+    let source_directory = format!("{owner_id}/{}", body.folder);
+    let source_path = storage.resolve_photo(&source_directory);
+    let (photos, subfolder_photos): (Vec<_>, Vec<_>) = state
+        .folders_repo
+        .get_folder_tree_photos(&owner_id, &body.folder)
+        .await?
+        .into_iter()
+        .partition(|photo| photo.folder.as_deref() == Some(body.folder.as_str()));
+
+    if photos.is_empty() && subfolder_photos.is_empty() && !source_path.is_dir() {
+        return Err(folder_not_found());
+    }
+
+    let has_subdirectories = task::spawn_blocking(move || has_subdirectories(&source_path))
+        .await
+        .map_err(internal_error)?;
+    if !subfolder_photos.is_empty() || has_subdirectories {
+        return Err(StatusError::new_status(
+            "The folder has subfolders, move them out before merging it",
+            StatusCode::CONFLICT,
+        ));
+    }
+
+    // Trashed photos have no file in the target folder, but they must not share a name
+    // with another photo once restored
+    let mut taken_names = state
+        .folders_repo
+        .get_folder_photos(&owner_id, &body.into)
+        .await?
+        .into_iter()
+        .map(|photo| photo.name)
+        .collect::<HashSet<_>>();
+
+    info!(
+        "Merging folder {source_directory} into {owner_id}/{}",
+        body.into
+    );
+
+    let mut moved_photos = Vec::with_capacity(photos.len());
+    for photo in &photos {
+        let mut moved_photo = Photo {
+            folder: Some(body.into.clone()),
+            ..photo.clone()
+        };
+
+        moved_photo.name =
+            storage.available_name_excluding(moved_photo.partial_path(), &taken_names);
+        taken_names.insert(moved_photo.name.clone());
+
+        // Trashed photos keep their file in the trash, it is moved once the database is updated
+        if !photo.is_trashed()
+            && let Err(e) = storage.move_photo(photo.partial_path(), moved_photo.partial_path())
+        {
+            undo_merge_moves(&state, &moved_photos);
+            return Err(StatusError::create(format!(
+                "Failed moving photo {}: {e}",
+                photo.id
+            )));
+        }
+
+        moved_photos.push((photo, moved_photo));
+    }
+
+    let updates = moved_photos
+        .iter()
+        .map(|(_, moved_photo)| (moved_photo.id, moved_photo.name.clone()))
+        .collect::<Vec<_>>();
+
+    if let Err(e) = state
+        .folders_repo
+        .merge_folder(&owner_id, &body.folder, &body.into, &updates)
+        .await
+    {
+        undo_merge_moves(&state, &moved_photos);
+        return Err(e);
+    }
+
+    for (photo, moved_photo) in &moved_photos {
+        if photo.is_trashed() {
+            move_trashed_photo(&state, photo, moved_photo);
+        }
+    }
+
+    // Only removed if nothing else was left behind
+    let _ = fs::remove_dir(storage.resolve_photo(&source_directory)).await;
+
+    Ok(Json(
+        get_folder(&state, &user.id, &owner_id, &body.into).await?,
+    ))
+}
+
+/// Whether the folder contains any other folder, hidden ones included
+fn has_subdirectories(path: &Path) -> bool {
+    std::fs::read_dir(path).is_ok_and(|mut entries| {
+        entries.any(|entry| entry.is_ok_and(|entry| entry.path().is_dir()))
+    })
+}
+
+/// Puts the already moved files back where the database expects them
+fn undo_merge_moves(state: &AppState, moved_photos: &[(&Photo, Photo)]) {
+    for (photo, moved_photo) in moved_photos {
+        if !photo.is_trashed() {
+            let _ = state
+                .storage
+                .move_photo(moved_photo.partial_path(), photo.partial_path());
+        }
+    }
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct DeleteFolderQuery {
+    user_name: Option<String>,
+    folder: String,
+}
+
+///
+/// Deletes an empty folder along with its shares and share links.
+/// Trashed photos don't keep a folder from being deleted, they recreate it when restored.
+///
+async fn delete_folder(
+    State(state): State<AppState>,
+    Query(query): Query<DeleteFolderQuery>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let owner_id = folder_owner(&auth, query.user_name)?;
+    check_folder_name(&query.folder)?;
+
+    check_folder_access(
+        &state,
+        auth.user,
+        &owner_id,
+        &query.folder,
+        SharePermission::Contribute,
+    )
+    .await?;
+
+    if state
+        .folders_repo
+        .has_photos(&owner_id, &query.folder)
+        .await?
+    {
+        return Err(StatusError::new_status(
+            "The folder still contains photos",
+            StatusCode::CONFLICT,
+        ));
+    }
+
+    let directory = state
+        .storage
+        .resolve_photo(format!("{owner_id}/{}", query.folder));
+
+    match fs::remove_dir(&directory).await {
+        Ok(()) => {}
+        Err(e) if e.kind() == std::io::ErrorKind::DirectoryNotEmpty => {
+            return Err(StatusError::new_status(
+                "The folder still contains files",
+                StatusCode::CONFLICT,
+            ));
+        }
+        Err(e) if e.kind() == std::io::ErrorKind::NotFound => {
+            if state
+                .folders_repo
+                .get_folder_photos(&owner_id, &query.folder)
+                .await?
+                .is_empty()
+            {
+                return Err(folder_not_found());
+            }
+        }
+        Err(e) => {
+            return Err(StatusError::create(format!(
+                "Failed deleting the folder: {e}"
+            )));
+        }
+    }
+
+    state
+        .folders_repo
+        .delete_folder(&owner_id, &query.folder)
+        .await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
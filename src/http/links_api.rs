@@ -0,0 +1,145 @@
+use std::collections::HashSet;
+
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{delete, get};
+use axum::{Json, Router};
+use rand::distributions::{Alphanumeric, DistString};
+use time::serde::timestamp;
+use time::OffsetDateTime;
+use tokio::task;
+
+use crate::http::utils::status_error::StatusError;
+use crate::http::utils::{check_is_owner, AuthSession, AxumResult};
+use crate::http::AppState;
+use crate::model::share_link::ShareLink;
+use crate::utils::internal_error;
+use crate::utils::password_hash::generate_hash_from_password;
+
+pub fn router(app_state: AppState) -> Router {
+    Router::new()
+        .route("/", get(list_links).post(create_link))
+        .route("/{link_id}", delete(delete_link))
+        .with_state(app_state)
+}
+
+async fn list_links(
+    State(state): State<AppState>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    Ok(Json(state.share_links_repo.get_links(user.id).await?))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateLinkBody {
+    /// The selection of photos to share
+    #[serde(default)]
+    photo_ids: Vec<i64>,
+    /// The folder to share, instead of a selection of photos
+    folder: Option<String>,
+    #[serde(default, with = "timestamp::option")]
+    expires_at: Option<OffsetDateTime>,
+    password: Option<String>,
+    #[serde(default)]
+    allow_download: bool,
+}
+
+///
+/// Creates a link to a folder of the user or to a selection of photos.
+/// Only the user's own photos and the public ones can be shared through a link.
+///
+async fn create_link(
+    State(state): State<AppState>,
+    auth: AuthSession,
+    Json(body): Json<CreateLinkBody>,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    match (&body.folder, body.photo_ids.is_empty()) {
+        (Some(folder), true) if !folder.is_empty() => {}
+        (None, false) => {
+            let photos = state.photos_repo.get_photos(&body.photo_ids).await?;
+            let found_ids = photos.iter().map(|photo| photo.id).collect::<HashSet<_>>();
+            if let Some(photo_id) = body
+                .photo_ids
+                .iter()
+                .find(|photo_id| !found_ids.contains(photo_id))
+            {
+                return Err(StatusError::new_status(
+                    format!("Photo {photo_id} not found"),
+                    StatusCode::NOT_FOUND,
+                ));
+            }
+
+            for photo in &photos {
+                check_is_owner(Some(user.clone()), photo)?;
+            }
+        }
+        _ => {
+            return Err(StatusError::new_status(
+                "Either a folder or some photos must be shared",
+                StatusCode::BAD_REQUEST,
+            ))
+        }
+    }
+
+    if body
+        .expires_at
+        .is_some_and(|expires_at| expires_at <= OffsetDateTime::now_utc())
+    {
+        return Err(StatusError::new_status(
+            "The expiry date must be in the future",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let password_hash = match body.password.filter(|password| !password.is_empty()) {
+        Some(password) => Some(
+            task::spawn_blocking(move || generate_hash_from_password(password))
+                .await
+                .map_err(internal_error)?,
+        ),
+        None => None,
+    };
+
+    let link = ShareLink {
+        id: 0, // Set by the database
+        token: Alphanumeric.sample_string(&mut rand::thread_rng(), 32),
+        user_id: user.id,
+        folder: body.folder,
+        expires_at: body.expires_at,
+        password_hash,
+        allow_download: body.allow_download,
+        created_at: OffsetDateTime::now_utc(),
+    };
+
+    let link = state
+        .share_links_repo
+        .insert_link(&link, &body.photo_ids)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(link)))
+}
+
+async fn delete_link(
+    State(state): State<AppState>,
+    Path(link_id): Path<i64>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let link = state
+        .share_links_repo
+        .get_link(link_id)
+        .await?
+        .filter(|link| link.user_id == user.id)
+        .ok_or_else(|| StatusError::new_status("Link not found", StatusCode::NOT_FOUND))?;
+
+    state.share_links_repo.delete_link(link.id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -1,3 +1,6 @@
+// The handlers return axum's `ErrorResponse` as their error type, which is large by design
+#![allow(clippy::result_large_err)]
+
 use axum::extract::DefaultBodyLimit;
 use axum::routing::get;
 use axum::Router;
@@ -12,11 +15,27 @@ use tower_http::{cors, trace};
 use tower_sessions_sqlx_store::SqliteStore;
 use tracing::{warn, Level};
 
+use crate::geocoder::ReverseGeocoder;
+use crate::http::uploads_api::ActiveUploads;
+use crate::repo::albums_repo::AlbumsRepository;
+use crate::repo::comments_repo::CommentsRepository;
+use crate::repo::folders_repo::FoldersRepository;
 use crate::repo::photos_repo::PhotosRepository;
+use crate::repo::ratings_repo::RatingsRepository;
+use crate::repo::share_links_repo::ShareLinksRepository;
+use crate::repo::shares_repo::SharesRepository;
+use crate::repo::tags_repo::TagsRepository;
+use crate::repo::uploads_repo::UploadsRepository;
 use crate::repo::users_repo::UsersRepository;
 use crate::utils::storage_resolver::StorageResolver;
 
+mod albums_api;
+mod folders_api;
+mod links_api;
 mod photos_api;
+mod shared_api;
+mod shares_api;
+mod uploads_api;
 mod users_api;
 mod utils;
 
@@ -30,7 +49,13 @@ pub fn router(app_state: AppState, session_store: SqliteStore) -> Router {
     Router::new()
         .route("/", get(|| async { "Hello, World!" }))
         .merge(users_api::router())
-        .nest("/photos", photos_api::router(app_state))
+        .nest("/photos", photos_api::router(app_state.clone()))
+        .nest("/uploads", uploads_api::router(app_state.clone()))
+        .nest("/albums", albums_api::router(app_state.clone()))
+        .nest("/folders", folders_api::router(app_state.clone()))
+        .nest("/shares", shares_api::router(app_state.clone()))
+        .nest("/links", links_api::router(app_state.clone()))
+        .nest("/shared", shared_api::router(app_state))
         .layer(
             TraceLayer::new_for_http()
                 .make_span_with(trace::DefaultMakeSpan::new().level(Level::INFO))
@@ -46,14 +71,34 @@ pub struct AppState {
     pub storage: StorageResolver,
     pub users_repo: UsersRepository,
     pub photos_repo: PhotosRepository,
+    pub ratings_repo: RatingsRepository,
+    pub uploads_repo: UploadsRepository,
+    pub albums_repo: AlbumsRepository,
+    pub comments_repo: CommentsRepository,
+    pub folders_repo: FoldersRepository,
+    pub shares_repo: SharesRepository,
+    pub share_links_repo: ShareLinksRepository,
+    pub tags_repo: TagsRepository,
+    pub geocoder: ReverseGeocoder,
+    pub active_uploads: ActiveUploads,
 }
 
 impl AppState {
-    pub fn new(pool: SqlitePool, storage: StorageResolver) -> Self {
+    pub fn new(pool: SqlitePool, storage: StorageResolver, geocoder: ReverseGeocoder) -> Self {
         Self {
             storage,
             users_repo: UsersRepository::new(pool.clone()),
-            photos_repo: PhotosRepository::new(pool),
+            photos_repo: PhotosRepository::new(pool.clone()),
+            ratings_repo: RatingsRepository::new(pool.clone()),
+            uploads_repo: UploadsRepository::new(pool.clone()),
+            albums_repo: AlbumsRepository::new(pool.clone()),
+            comments_repo: CommentsRepository::new(pool.clone()),
+            folders_repo: FoldersRepository::new(pool.clone()),
+            shares_repo: SharesRepository::new(pool.clone()),
+            share_links_repo: ShareLinksRepository::new(pool.clone()),
+            tags_repo: TagsRepository::new(pool),
+            geocoder,
+            active_uploads: ActiveUploads::default(),
         }
     }
 }
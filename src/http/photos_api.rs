@@ -1,124 +1,507 @@
+use std::collections::HashSet;
 use std::string::ToString;
 
-use axum::response::ErrorResponse;
 use axum::{
     extract::Multipart,
     extract::{Path, Query, State},
-    http::StatusCode,
+    http::{HeaderMap, StatusCode},
     response::IntoResponse,
-    routing::{delete, get, post},
+    routing::{delete, get, post, put},
     Json, Router,
 };
+use rand::distributions::{Alphanumeric, DistString};
+use serde::Serialize;
 use time::OffsetDateTime;
 use tokio::{fs, task};
-use tracing::{error, info};
+use tracing::info;
 
 use crate::http::utils::status_error::StatusError;
-use crate::http::utils::{file_to_response, write_field_to_file, AuthSession, AxumResult};
+use crate::http::utils::{
+    archive_to_response, check_folder_name, check_has_access, check_is_owner, file_to_response,
+    preview_to_response, save_staged_photo, write_field_to_file, AuthSession, AxumResult,
+    DownloadQuery, PreviewQuery, NO_CACHE,
+};
 use crate::http::AppState;
-use crate::model::photo::{Photo, PhotoBase, PhotoBody};
+use crate::model::photo::{Photo, PhotoBase, PhotoBody, PhotoCursor, PhotoListItem};
+use crate::model::photo_comment::{PhotoComment, MAX_COMMENT_LENGTH};
+use crate::model::photo_details::{PhotoDetails, MAX_CAPTION_LENGTH};
+use crate::model::photo_location::{BoundingBox, PhotoCluster, PhotoLocation};
+use crate::model::photo_rating::{ColorLabel, MAX_RATING};
+use crate::model::photo_share::SharePermission;
+use crate::model::place::PlaceLevel;
+use crate::model::tag::normalize_tag;
+use crate::model::timeline::{bucket_bounds, TimelineGranularity};
 use crate::model::user::{User, PUBLIC_USER_ID};
-use crate::previews;
-use crate::utils::{internal_error, read_exif};
+use crate::repo::photos_repo::{MediaType, PhotosFilter};
+use crate::utils::content_hash::parse_content_hash;
+use crate::utils::{content_hash, exif_date, file_name, internal_error, photo_metadata};
+use crate::{memories, trash};
 use time::serde::timestamp;
 
 pub fn router(app_state: AppState) -> Router {
     Router::new()
         .route("/", get(photos_list))
+        .route("/changes", get(photos_changes))
+        .route("/search", get(search_photos))
+        .route("/map", get(photos_map))
+        .route("/places", get(photos_places))
+        .route("/memories", get(photos_memories))
+        .route("/timeline", get(photos_timeline))
+        .route("/timeline/{bucket}", get(timeline_bucket_photos))
         .route("/download/{photo_id}", get(download_photo))
         .route("/preview/{photo_id}", get(preview_photo))
         .route("/exif/{photo_id}", get(get_photo_exif))
+        .route("/metadata/{photo_id}", get(get_photo_metadata))
+        .route("/details/{photo_id}", get(get_photo_details))
+        .route("/caption/{photo_id}", put(set_photo_caption))
+        .route("/tags", get(get_tags))
+        .route("/tags/{photo_id}", post(add_photo_tags))
+        .route("/tags/{photo_id}/{tag}", delete(remove_photo_tag))
         .route("/upload", post(upload_photo))
+        .route("/exists", post(photos_exist))
+        .route("/archive", get(archive_folder).post(archive_photos))
         .route("/delete/{photo_id}", delete(delete_photo))
         .route("/change_location/{photo_id}", post(change_photo_location))
+        .route("/rename/{photo_id}", post(rename_photo))
+        .route("/batch", post(batch_photos))
+        .route("/date", post(change_photos_date))
+        .route("/{photo_id}/comments", get(get_comments).post(add_comment))
+        .route(
+            "/{photo_id}/comments/{comment_id}",
+            put(edit_comment).delete(delete_comment),
+        )
+        .route("/ratings", get(get_ratings).post(rate_photos))
+        .route("/labels", post(label_photos))
         .route("/favorite", get(get_favorites))
         .route("/favorite/{photo_id}", post(add_favorite))
         .route("/favorite/{photo_id}", delete(delete_favorite))
+        .route("/trash", get(get_trash).delete(empty_trash))
+        .route("/trash/restore/{photo_id}", post(restore_photo))
+        .route("/trash/{photo_id}", delete(delete_photo_permanently))
         .with_state(app_state)
 }
 
-fn check_has_access(user: Option<User>, photo: &Photo) -> Result<User, ErrorResponse> {
-    let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
+const DEFAULT_PAGE_SIZE: u32 = 500;
+const MAX_PAGE_SIZE: u32 = 5000;
 
-    if photo.user_id() == &user.id || photo.user_id() == PUBLIC_USER_ID {
-        Ok(user)
-    } else {
-        Err(StatusError::new_status(
-            "You don't have access to this resource",
-            StatusCode::FORBIDDEN,
-        ))
-    }
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PhotosListQuery {
+    cursor: Option<String>,
+    #[serde(default, with = "timestamp::option")]
+    since: Option<OffsetDateTime>,
+    #[serde(default, with = "timestamp::option")]
+    until: Option<OffsetDateTime>,
+    folder: Option<String>,
+    tag: Option<String>,
+    min_rating: Option<i64>,
+    color_label: Option<ColorLabel>,
+    limit: Option<u32>,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhotosPage {
+    photos: Vec<PhotoListItem>,
+    /// Cursor to pass back in order to get the next page, `None` if this was the last one
+    next_cursor: Option<String>,
 }
 
 async fn photos_list(
     State(state): State<AppState>,
+    Query(query): Query<PhotosListQuery>,
     auth: AuthSession,
 ) -> AxumResult<impl IntoResponse> {
-    let user = auth.user.ok_or(StatusCode::BAD_REQUEST)?;
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
 
-    Ok(Json(
+    let filter = PhotosFilter {
+        since: query.since,
+        until: query.until,
+        folder: query.folder,
+        tag: query.tag,
+        min_rating: query.min_rating,
+        color_label: query.color_label,
+        ..Default::default()
+    };
+
+    photos_page(&state, &user.id, &filter, query.cursor, query.limit).await
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct SearchQuery {
+    cursor: Option<String>,
+    limit: Option<u32>,
+    q: Option<String>,
+    name: Option<String>,
+    folder: Option<String>,
+    #[serde(default, with = "timestamp::option")]
+    since: Option<OffsetDateTime>,
+    #[serde(default, with = "timestamp::option")]
+    until: Option<OffsetDateTime>,
+    camera_model: Option<String>,
+    media_type: Option<MediaType>,
+    #[serde(default)]
+    favorites: bool,
+    tag: Option<String>,
+    min_rating: Option<i64>,
+    color_label: Option<ColorLabel>,
+    min_file_size: Option<i64>,
+    country: Option<String>,
+    region: Option<String>,
+    city: Option<String>,
+}
+
+async fn search_photos(
+    State(state): State<AppState>,
+    Query(query): Query<SearchQuery>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let filter = PhotosFilter {
+        since: query.since,
+        until: query.until,
+        folder: query.folder,
+        name: query.name.filter(|name| !name.is_empty()),
+        camera_model: query.camera_model.filter(|model| !model.is_empty()),
+        media_type: query.media_type,
+        favorites_only: query.favorites,
+        tag: query.tag,
+        min_rating: query.min_rating,
+        color_label: query.color_label,
+        min_file_size: query.min_file_size,
+        country: query.country,
+        region: query.region,
+        city: query.city,
+    };
+
+    if let Some(text) = query.q.filter(|text| !text.trim().is_empty()) {
+        // Results ranked by relevance are not paginated
+        let limit = query
+            .limit
+            .unwrap_or(DEFAULT_PAGE_SIZE)
+            .clamp(1, MAX_PAGE_SIZE);
+        let photos = state
+            .photos_repo
+            .search_photos(&user.id, &text, &filter, limit)
+            .await?;
+
+        return Ok(Json(PhotosPage {
+            photos: list_items(&state, photos).await?,
+            next_cursor: None,
+        }));
+    }
+
+    photos_page(&state, &user.id, &filter, query.cursor, query.limit).await
+}
+
+const DEFAULT_MEMORIES_PER_YEAR: u32 = 5;
+const MAX_MEMORIES_PER_YEAR: u32 = 50;
+const MAX_MEMORIES_WINDOW: u8 = 15;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MemoriesQuery {
+    /// Today when missing
+    #[serde(default, with = "timestamp::option")]
+    date: Option<OffsetDateTime>,
+    /// Days before and after the date to also include
+    #[serde(default)]
+    window: u8,
+    per_year: Option<u32>,
+    #[serde(default)]
+    favorites_first: bool,
+}
+
+/// Returns the photos taken on the same day of the year in the previous years
+async fn photos_memories(
+    State(state): State<AppState>,
+    Query(query): Query<MemoriesQuery>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let date = query.date.unwrap_or_else(OffsetDateTime::now_utc).date();
+    let window = query.window.min(MAX_MEMORIES_WINDOW);
+    let per_year = query
+        .per_year
+        .unwrap_or(DEFAULT_MEMORIES_PER_YEAR)
+        .clamp(1, MAX_MEMORIES_PER_YEAR);
+
+    // Photos around this year's anniversary of the date are not memories yet
+    let before = date
+        .checked_sub(time::Duration::days(i64::from(window)))
+        .ok_or_else(|| StatusError::new_status("Invalid date", StatusCode::BAD_REQUEST))?
+        .midnight()
+        .assume_utc();
+    let photos = state
+        .photos_repo
+        .get_photos_on_days(&user.id, &memories::month_days(date, window), before)
+        .await?;
+
+    let favorites = if query.favorites_first {
         state
             .photos_repo
-            .get_photos_by_user_and_public(user.id)
-            .await?,
-    ))
+            .get_favorite_photos(&user.id)
+            .await?
+            .into_iter()
+            .collect()
+    } else {
+        HashSet::new()
+    };
+
+    Ok(Json(memories::pick_memories(
+        date,
+        photos,
+        &favorites,
+        query.favorites_first,
+        per_year as usize,
+    )))
 }
 
-async fn preview_photo(
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TimelineQuery {
+    #[serde(default)]
+    granularity: TimelineGranularity,
+}
+
+async fn photos_timeline(
     State(state): State<AppState>,
-    Path(photo_id): Path<i64>,
+    Query(query): Query<TimelineQuery>,
     auth: AuthSession,
-) -> impl IntoResponse {
-    let AppState {
-        storage,
-        users_repo: _users_repo,
-        photos_repo,
-    } = state;
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let buckets = state
+        .photos_repo
+        .get_timeline(&user.id, query.granularity)
+        .await?;
 
-    let photo = photos_repo.get_photo(photo_id).await?;
-    check_has_access(auth.user, &photo)?;
+    Ok(Json(buckets))
+}
 
-    let photo_path = storage.resolve_photo(photo.partial_path());
-    let preview_path = storage.resolve_preview(photo.partial_preview_path());
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BucketPhotosQuery {
+    cursor: Option<String>,
+    limit: Option<u32>,
+}
 
-    let preview_generated = if !preview_path.exists() {
-        let photo_path_clone = photo_path.clone();
-        let preview_path_clone = preview_path.clone();
+/// Returns a page of the photos in the timeline bucket with the given key
+async fn timeline_bucket_photos(
+    State(state): State<AppState>,
+    Path(bucket): Path<String>,
+    Query(query): Query<BucketPhotosQuery>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
 
-        task::spawn_blocking(move || {
-            previews::generate_preview(photo_path_clone, preview_path_clone)
-        })
-        .await
-        .unwrap()
-    } else {
-        Ok(())
+    let (since, until) = bucket_bounds(&bucket).ok_or_else(|| {
+        StatusError::new_status(
+            "The bucket must have the format YYYY, YYYY-MM or YYYY-MM-DD",
+            StatusCode::BAD_REQUEST,
+        )
+    })?;
+    let filter = PhotosFilter {
+        since: Some(since),
+        until: Some(until),
+        ..Default::default()
     };
 
-    let path = match preview_generated {
-        Ok(_) => preview_path,
-        Err(e) => {
-            error!(
-                "Preview generation failed for video: {}\nCause: {e}",
-                photo_path.display()
-            );
-            photo_path
-        }
+    photos_page(&state, &user.id, &filter, query.cursor, query.limit).await
+}
+
+/// Returns a page of the photos matching the filter, continuing from the cursor
+async fn photos_page(
+    state: &AppState,
+    user_id: &str,
+    filter: &PhotosFilter,
+    cursor: Option<String>,
+    limit: Option<u32>,
+) -> AxumResult<Json<PhotosPage>> {
+    let cursor = cursor
+        .map(|cursor| cursor.parse::<PhotoCursor>())
+        .transpose()
+        .map_err(|_| StatusError::new_status("Invalid cursor", StatusCode::BAD_REQUEST))?;
+    let limit = limit.unwrap_or(DEFAULT_PAGE_SIZE).clamp(1, MAX_PAGE_SIZE);
+
+    // Fetch an extra photo to find out whether there is a next page
+    let mut photos = state
+        .photos_repo
+        .get_photos_page(user_id, filter, cursor, limit + 1)
+        .await?;
+
+    let next_cursor = if photos.len() > limit as usize {
+        photos.truncate(limit as usize);
+        photos
+            .last()
+            .map(|photo| PhotoCursor::from(photo).to_string())
+    } else {
+        None
     };
 
-    file_to_response(&path).await
+    Ok(Json(PhotosPage {
+        photos: list_items(state, photos).await?,
+        next_cursor,
+    }))
+}
+
+/// Adds the number of comments to the listed photos
+async fn list_items(state: &AppState, photos: Vec<Photo>) -> AxumResult<Vec<PhotoListItem>> {
+    let photo_ids = photos.iter().map(|photo| photo.id).collect::<Vec<_>>();
+    let comment_counts = state.comments_repo.get_comment_counts(&photo_ids).await?;
+
+    Ok(photos
+        .into_iter()
+        .map(|photo| PhotoListItem {
+            comment_count: comment_counts.get(&photo.id).copied().unwrap_or_default(),
+            photo,
+        })
+        .collect())
+}
+
+/// From this zoom level on the photos are returned individually instead of clustered
+const MIN_PHOTOS_ZOOM: u8 = 16;
+const MAX_ZOOM: u8 = 22;
+/// A map tile is split into this many cells horizontally and vertically when clustering
+const CLUSTER_CELLS_PER_TILE: f64 = 4.0;
+const MAX_MAP_PHOTOS: u32 = 5000;
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct MapQuery {
+    bbox: String,
+    zoom: u8,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct PhotosMap {
+    clusters: Vec<PhotoCluster>,
+    photos: Vec<PhotoLocation>,
+}
+
+async fn photos_map(
+    State(state): State<AppState>,
+    Query(query): Query<MapQuery>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let area = query.bbox.parse::<BoundingBox>().map_err(|_| {
+        StatusError::new_status(
+            "The bbox must have the format <west>,<south>,<east>,<north>",
+            StatusCode::BAD_REQUEST,
+        )
+    })?;
+    if query.zoom > MAX_ZOOM {
+        return Err(StatusError::new_status(
+            format!("The zoom can be at most {MAX_ZOOM}"),
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    if query.zoom >= MIN_PHOTOS_ZOOM {
+        let photos = state
+            .photos_repo
+            .get_photo_locations(&user.id, &area, MAX_MAP_PHOTOS)
+            .await?;
+
+        return Ok(Json(PhotosMap {
+            clusters: Vec::new(),
+            photos,
+        }));
+    }
+
+    // At zoom level z the world is 2^z tiles wide
+    let cell_size = 360.0 / (f64::from(1u32 << query.zoom) * CLUSTER_CELLS_PER_TILE);
+    let clusters = state
+        .photos_repo
+        .get_photo_clusters(&user.id, &area, cell_size)
+        .await?;
+
+    Ok(Json(PhotosMap {
+        clusters,
+        photos: Vec::new(),
+    }))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct PlacesQuery {
+    #[serde(default)]
+    group_by: PlaceLevel,
+}
+
+async fn photos_places(
+    State(state): State<AppState>,
+    Query(query): Query<PlacesQuery>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let places = state
+        .photos_repo
+        .get_place_groups(&user.id, query.group_by)
+        .await?;
+
+    Ok(Json(places))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChangesQuery {
+    #[serde(default)]
+    since: i64,
+}
+
+async fn photos_changes(
+    State(state): State<AppState>,
+    Query(query): Query<ChangesQuery>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    Ok(Json(
+        state.photos_repo.get_changes(user.id, query.since).await?,
+    ))
+}
+
+async fn preview_photo(
+    State(state): State<AppState>,
+    Path(photo_id): Path<i64>,
+    Query(query): Query<PreviewQuery>,
+    headers: HeaderMap,
+    auth: AuthSession,
+) -> impl IntoResponse {
+    // Trashed photos still have previews, so they can be shown in the trash
+    let photo = state
+        .photos_repo
+        .get_photo_including_trashed(photo_id)
+        .await?;
+    if photo.is_trashed() {
+        check_is_owner(auth.user, &photo)?;
+    } else {
+        check_has_access(&state, auth.user, &photo, SharePermission::Read).await?;
+    }
+
+    preview_to_response(&state.storage, &photo, &headers, &query, true).await
 }
 
 async fn download_photo(
     State(state): State<AppState>,
     Path(photo_id): Path<i64>,
+    Query(query): Query<DownloadQuery>,
+    headers: HeaderMap,
     auth: AuthSession,
 ) -> impl IntoResponse {
     let photo = state.photos_repo.get_photo(photo_id).await?;
-    check_has_access(auth.user, &photo)?;
+    check_has_access(&state, auth.user, &photo, SharePermission::Read).await?;
 
     let photo_path = state.storage.resolve_photo(photo.partial_path());
-
-    file_to_response(&photo_path).await
+    file_to_response(&photo_path, &headers, query.disposition(), NO_CACHE).await
 }
 
 async fn get_photo_exif(
@@ -127,10 +510,10 @@ async fn get_photo_exif(
     auth: AuthSession,
 ) -> impl IntoResponse {
     let photo = state.photos_repo.get_photo(photo_id).await?;
-    check_has_access(auth.user, &photo)?;
+    check_has_access(&state, auth.user, &photo, SharePermission::Read).await?;
 
     let path = state.storage.resolve_photo(photo.partial_path());
-    let exif = task::spawn_blocking(move || read_exif(path))
+    let exif = task::spawn_blocking(move || photo_metadata::read_exif_fields(path))
         .await
         .map_err(internal_error)?;
 
@@ -143,6 +526,150 @@ async fn get_photo_exif(
     }
 }
 
+/// Returns the metadata stored when the photo was added
+async fn get_photo_metadata(
+    State(state): State<AppState>,
+    Path(photo_id): Path<i64>,
+    auth: AuthSession,
+) -> impl IntoResponse {
+    let photo = state.photos_repo.get_photo(photo_id).await?;
+    check_has_access(&state, auth.user, &photo, SharePermission::Read).await?;
+
+    let metadata = match state.photos_repo.get_photo_metadata(photo.id).await? {
+        Some(metadata) => metadata,
+        None => {
+            // Added before metadata was stored, extract it now so it is only done once
+            let path = state.storage.resolve_photo(photo.partial_path());
+            let mut metadata = task::spawn_blocking(move || photo_metadata::read_metadata(path))
+                .await
+                .map_err(internal_error)?;
+            state.geocoder.assign_place(&mut metadata);
+
+            state
+                .photos_repo
+                .insert_metadata(&[(photo.id, metadata.clone())])
+                .await
+                .map_err(internal_error)?;
+            metadata
+        }
+    };
+
+    match metadata.is_empty() {
+        false => Ok(Json(metadata)),
+        true => Err(StatusError::new_status(
+            "Exif data not found",
+            StatusCode::NOT_FOUND,
+        )),
+    }
+}
+
+async fn photo_details(state: &AppState, photo: Photo) -> AxumResult<PhotoDetails> {
+    Ok(PhotoDetails {
+        caption: state.photos_repo.get_caption(photo.id).await?,
+        tags: state.tags_repo.get_photo_tags(photo.id).await?,
+        photo,
+    })
+}
+
+/// Returns the photo along with its caption and tags
+async fn get_photo_details(
+    State(state): State<AppState>,
+    Path(photo_id): Path<i64>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let photo = state.photos_repo.get_photo(photo_id).await?;
+    check_has_access(&state, auth.user, &photo, SharePermission::Read).await?;
+
+    Ok(Json(photo_details(&state, photo).await?))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CaptionBody {
+    /// Removes the caption when missing or empty
+    caption: Option<String>,
+}
+
+async fn set_photo_caption(
+    State(state): State<AppState>,
+    Path(photo_id): Path<i64>,
+    auth: AuthSession,
+    Json(body): Json<CaptionBody>,
+) -> AxumResult<impl IntoResponse> {
+    let photo = state.photos_repo.get_photo(photo_id).await?;
+    check_has_access(&state, auth.user, &photo, SharePermission::Contribute).await?;
+
+    let caption = body
+        .caption
+        .as_deref()
+        .map(str::trim)
+        .filter(|caption| !caption.is_empty());
+
+    if caption.is_some_and(|caption| caption.chars().count() > MAX_CAPTION_LENGTH) {
+        return Err(StatusError::new_status(
+            format!("The caption can't be longer than {MAX_CAPTION_LENGTH} characters"),
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    state.photos_repo.set_caption(photo.id, caption).await?;
+
+    Ok(Json(photo_details(&state, photo).await?))
+}
+
+/// Returns the tags of the photos the user has access to, the most used first
+async fn get_tags(
+    State(state): State<AppState>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    Ok(Json(state.tags_repo.get_tags(&user.id).await?))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct TagsBody {
+    tags: Vec<String>,
+}
+
+async fn add_photo_tags(
+    State(state): State<AppState>,
+    Path(photo_id): Path<i64>,
+    auth: AuthSession,
+    Json(body): Json<TagsBody>,
+) -> AxumResult<impl IntoResponse> {
+    let photo = state.photos_repo.get_photo(photo_id).await?;
+    check_has_access(&state, auth.user, &photo, SharePermission::Contribute).await?;
+
+    let tags = body
+        .tags
+        .iter()
+        .map(|tag| normalize_tag(tag))
+        .collect::<Result<Vec<_>, _>>()
+        .map_err(|e| StatusError::new_status(e, StatusCode::BAD_REQUEST))?;
+
+    state.tags_repo.add_photo_tags(photo.id, &tags).await?;
+
+    Ok(Json(photo_details(&state, photo).await?))
+}
+
+async fn remove_photo_tag(
+    State(state): State<AppState>,
+    Path((photo_id, tag)): Path<(i64, String)>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let photo = state.photos_repo.get_photo(photo_id).await?;
+    check_has_access(&state, auth.user, &photo, SharePermission::Contribute).await?;
+
+    state
+        .tags_repo
+        .remove_photo_tag(photo.id, tag.trim())
+        .await?;
+
+    Ok(Json(photo_details(&state, photo).await?))
+}
+
 #[derive(Debug, serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct UploadDataQuery {
@@ -151,6 +678,11 @@ struct UploadDataQuery {
     folder_name: Option<String>,
     #[serde(default)]
     make_public: bool,
+    /// Uploads to a folder of another user, which must be shared with contribute permission
+    target_user_name: Option<String>,
+    /// Hex encoded SHA-256 of the file, if it matches an existing photo that one is returned
+    /// without uploading the file again
+    hash: Option<String>,
 }
 
 async fn upload_photo(
@@ -160,6 +692,48 @@ async fn upload_photo(
     mut payload: Multipart,
 ) -> AxumResult<impl IntoResponse> {
     let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+    if let Some(folder_name) = &query.folder_name {
+        check_folder_name(folder_name)?;
+    }
+
+    let owner_id = match query.target_user_name {
+        Some(target_user_name) if target_user_name != user.id => {
+            let folder_name = query.folder_name.as_ref().ok_or_else(|| {
+                StatusError::new_status(
+                    "Uploading for another user requires a shared folder",
+                    StatusCode::BAD_REQUEST,
+                )
+            })?;
+            let permission = state
+                .shares_repo
+                .get_folder_permission(&user.id, &target_user_name, folder_name)
+                .await?;
+
+            if permission != Some(SharePermission::Contribute) {
+                return Err(StatusError::new_status(
+                    "You can't upload to this folder",
+                    StatusCode::FORBIDDEN,
+                ));
+            }
+            target_user_name
+        }
+        _ if query.make_public => String::from(PUBLIC_USER_ID),
+        _ => user.id.clone(),
+    };
+
+    let expected_hash = query
+        .hash
+        .map(|hash| {
+            parse_content_hash(hash)
+                .ok_or_else(|| StatusError::new_status("Invalid hash", StatusCode::BAD_REQUEST))
+        })
+        .transpose()?;
+
+    if let Some(hash) = &expected_hash
+        && let Some(existing) = state.photos_repo.get_photo_by_hash(&owner_id, hash).await?
+    {
+        return Ok(Json(existing));
+    }
 
     let field = payload
         .next_field()
@@ -170,67 +744,173 @@ async fn upload_photo(
         .file_name()
         .or(field.name())
         .ok_or_else(|| StatusError::new_status("Multipart has no name", StatusCode::BAD_REQUEST))?;
+    file_name::validate_name(file_name).map_err(|e| {
+        StatusError::new_status(format!("Invalid filename: {e}"), StatusCode::BAD_REQUEST)
+    })?;
 
     let mut new_photo_body = PhotoBody::new(
-        if query.make_public {
-            String::from(PUBLIC_USER_ID)
-        } else {
-            user.id
-        },
+        owner_id.clone(),
         String::from(file_name),
         query.time_created,
         0, // To be set after it is written to disk
         query.folder_name,
     );
 
-    let photo_path = state.storage.resolve_photo(new_photo_body.partial_path());
-    if let Some(parent) = photo_path.parent() {
-        if !parent.exists() {
-            fs::create_dir_all(parent).await.map_err(internal_error)?;
-        }
-    }
+    // The file is written to the staging area first so that it never overwrites an existing one
+    let staged_path = state.storage.resolve_upload(format!(
+        "multipart-{}",
+        Alphanumeric.sample_string(&mut rand::thread_rng(), 32)
+    ));
 
-    info!("Uploading file to {}", photo_path.display());
+    info!("Uploading file to {}", staged_path.display());
 
-    match write_field_to_file(field, &photo_path).await {
-        Ok(file_size) => new_photo_body.set_file_size(file_size as i64),
+    match write_field_to_file(field, &staged_path).await {
+        Ok((_, hash))
+            if expected_hash
+                .as_ref()
+                .is_some_and(|expected| expected != &hash) =>
+        {
+            let _ = fs::remove_file(staged_path).await;
+            return Err(StatusError::new_status(
+                "The uploaded file doesn't match the given hash",
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+        Ok((file_size, hash)) => {
+            new_photo_body.set_file_size(file_size as i64);
+            new_photo_body.set_content_hash(hash);
+        }
         Err(e) => {
             // Upload failed, delete the file
-            let _ = fs::remove_file(photo_path).await;
+            let _ = fs::remove_file(staged_path).await;
             return Err(e);
         }
     }
 
-    match state.photos_repo.insert_photo(&new_photo_body).await {
+    match save_staged_photo(&state, &owner_id, &staged_path, new_photo_body).await {
         Ok(photo) => Ok(Json(photo)),
         Err(e) => {
             // Insertion failed, delete the file
-            let _ = fs::remove_file(photo_path).await;
+            let _ = fs::remove_file(staged_path).await;
             Err(e)
         }
     }
 }
 
-async fn delete_photo(
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ExistsBody {
+    hashes: Vec<String>,
+}
+
+/// Returns which of the given content hashes are already on the server
+async fn photos_exist(
     State(state): State<AppState>,
-    Path(photo_id): Path<i64>,
     auth: AuthSession,
-) -> impl IntoResponse {
-    let photo = state.photos_repo.get_photo(photo_id).await?;
-    check_has_access(auth.user, &photo)?;
+    Json(body): Json<ExistsBody>,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
 
-    let _ = fs::remove_file(state.storage.resolve_preview(photo.partial_preview_path())).await;
+    let hashes = body
+        .hashes
+        .into_iter()
+        .filter_map(parse_content_hash)
+        .collect::<Vec<_>>();
 
-    match fs::remove_file(state.storage.resolve_photo(photo.partial_path())).await {
-        Ok(_) => match state.photos_repo.delete_photo(photo_id).await {
-            Ok(_count) => Ok("{\"deleted\": true}".to_string()),
-            _ => Err(StatusError::create("Failed to remove photo from database")),
-        },
-        Err(e) => Err(StatusError::create(format!("Failed to delete file: {e}"))),
-    }
+    Ok(Json(
+        state
+            .photos_repo
+            .get_existing_hashes(user.id, &hashes)
+            .await?,
+    ))
 }
 
-#[derive(serde::Deserialize)]
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchiveQuery {
+    folder: String,
+}
+
+/// Streams a ZIP with all the photos in the folder that are visible to the user
+async fn archive_folder(
+    State(state): State<AppState>,
+    Query(query): Query<ArchiveQuery>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let filter = PhotosFilter {
+        folder: Some(query.folder.clone()),
+        ..Default::default()
+    };
+    let photos = state
+        .photos_repo
+        .get_filtered_photos(&user.id, &filter)
+        .await?;
+
+    if photos.is_empty() {
+        return Err(StatusError::new_status(
+            "Folder not found",
+            StatusCode::NOT_FOUND,
+        ));
+    }
+
+    // Named after the innermost folder: "2019/Holidays" is downloaded as "Holidays.zip"
+    let archive_name = query.folder.rsplit('/').next().unwrap_or_default();
+    archive_to_response(state.storage, photos, archive_name)
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ArchiveBody {
+    photo_ids: Vec<i64>,
+}
+
+/// Streams a ZIP with the given photos, all of them must be accessible by the user
+async fn archive_photos(
+    State(state): State<AppState>,
+    auth: AuthSession,
+    Json(body): Json<ArchiveBody>,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let photos = state.photos_repo.get_photos(&body.photo_ids).await?;
+    let found_ids = photos.iter().map(|photo| photo.id).collect::<HashSet<_>>();
+    if let Some(photo_id) = body
+        .photo_ids
+        .iter()
+        .find(|photo_id| !found_ids.contains(photo_id))
+    {
+        return Err(StatusError::new_status(
+            format!("Photo {photo_id} not found"),
+            StatusCode::NOT_FOUND,
+        ));
+    }
+
+    for photo in &photos {
+        check_has_access(&state, Some(user.clone()), photo, SharePermission::Read).await?;
+    }
+
+    archive_to_response(state.storage, photos, "photos")
+}
+
+/// Moves the photo to the trash, from where it can still be restored
+async fn delete_photo(
+    State(state): State<AppState>,
+    Path(photo_id): Path<i64>,
+    auth: AuthSession,
+) -> impl IntoResponse {
+    let photo = state.photos_repo.get_photo(photo_id).await?;
+    check_has_access(&state, auth.user, &photo, SharePermission::Contribute).await?;
+
+    match trash::trash_photos(&state, vec![photo]).await?.pop() {
+        Some((_, Ok(_))) => Ok("{\"deleted\": true}".to_string()),
+        Some((_, Err(e))) => Err(StatusError::create(e)),
+        None => Err(StatusError::create("Failed to delete photo")),
+    }
+}
+
+#[derive(serde::Deserialize)]
 #[serde(rename_all = "camelCase")]
 struct ChangeLocationQuery {
     target_user_name: Option<String>,
@@ -243,24 +923,43 @@ async fn change_photo_location(
     Query(query): Query<ChangeLocationQuery>,
     auth: AuthSession,
 ) -> AxumResult<impl IntoResponse> {
-    let storage = state.storage;
+    let storage = &state.storage;
     let photo = state.photos_repo.get_photo(photo_id).await?;
-    check_has_access(auth.user, &photo)?;
+    // Moving a photo to another user is reserved to its owner, even with contribute access
+    check_is_owner(auth.user, &photo)?;
+    if let Some(folder_name) = &query.target_folder_name {
+        check_folder_name(folder_name)?;
+    }
 
     let target_user_name = query
         .target_user_name
         .unwrap_or(String::from(PUBLIC_USER_ID));
 
-    let changed_photo = Photo {
+    let mut changed_photo = Photo {
         id: photo.id(),
         user_id: target_user_name,
         name: photo.name().clone(),
         created_at: photo.created_at(),
         file_size: photo.file_size(),
         folder: query.target_folder_name.clone(),
+        content_hash: photo.content_hash().cloned(),
+        trashed_at: photo.trashed_at,
     };
 
     let source_path = photo.partial_path();
+    if changed_photo.partial_path() == source_path {
+        return Ok(Json(photo));
+    }
+
+    // Never overwrite a photo already in the target folder, same as the batch move
+    let taken_names = trashed_names(
+        &state,
+        &changed_photo.user_id,
+        changed_photo.folder.as_deref(),
+    )
+    .await?;
+    changed_photo.name =
+        storage.available_name_excluding(changed_photo.partial_path(), &taken_names);
     let destination_path = changed_photo.partial_path();
 
     info!("Moving photo from {source_path} to {destination_path}");
@@ -269,15 +968,624 @@ async fn change_photo_location(
         .move_photo(&source_path, &destination_path)
         .map_err(|e| StatusError::create(format!("Failed moving the photo: {e}")))?;
 
-    state
+    if state
         .photos_repo
         .update_photo(&changed_photo)
         .await
-        .map_err(|_| StatusError::create("Something went wrong moving the photo"))?;
+        .is_err()
+    {
+        // Put the file back where the database expects it
+        let _ = storage.move_photo(&destination_path, &source_path);
+        return Err(StatusError::create("Something went wrong moving the photo"));
+    }
 
     Ok(Json(changed_photo))
 }
 
+/// Trashed photos have no file in the folder, but they must not share a name with another photo
+/// once restored
+async fn trashed_names(
+    state: &AppState,
+    owner_id: &str,
+    folder: Option<&str>,
+) -> AxumResult<HashSet<String>> {
+    Ok(state
+        .photos_repo
+        .get_trashed_names(owner_id, folder)
+        .await?
+        .into_iter()
+        .collect())
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RenameBody {
+    name: String,
+}
+
+/// Renames the file of the photo, keeping it in the same folder and with the same extension
+async fn rename_photo(
+    State(state): State<AppState>,
+    Path(photo_id): Path<i64>,
+    auth: AuthSession,
+    Json(body): Json<RenameBody>,
+) -> AxumResult<impl IntoResponse> {
+    let storage = &state.storage;
+    let photo = state.photos_repo.get_photo(photo_id).await?;
+    check_has_access(&state, auth.user, &photo, SharePermission::Contribute).await?;
+
+    let mut name = body.name;
+    file_name::validate_name(&name)
+        .map_err(|e| StatusError::new_status(e, StatusCode::BAD_REQUEST))?;
+
+    // The extension is what tells if a file is a photo or a video, so it is kept as it was
+    match (
+        file_name::extension(photo.name()),
+        file_name::extension(&name),
+    ) {
+        (Some(_), None) => {
+            let extension = std::path::Path::new(photo.name())
+                .extension()
+                .unwrap_or_default()
+                .to_string_lossy();
+            name = format!("{name}.{extension}");
+            file_name::validate_name(&name)
+                .map_err(|e| StatusError::new_status(e, StatusCode::BAD_REQUEST))?;
+        }
+        (old, new) if old != new => {
+            return Err(StatusError::new_status(
+                "The extension of the photo can't be changed",
+                StatusCode::BAD_REQUEST,
+            ));
+        }
+        _ => {}
+    }
+
+    if &name == photo.name() {
+        return Ok(Json(photo));
+    }
+
+    let renamed_photo = Photo {
+        name,
+        ..photo.clone()
+    };
+
+    let source_path = photo.partial_path();
+    let destination_path = renamed_photo.partial_path();
+
+    info!("Renaming photo from {source_path} to {destination_path}");
+
+    storage
+        .rename_photo(&source_path, &destination_path)
+        .map_err(|e| match e.kind() {
+            std::io::ErrorKind::AlreadyExists => StatusError::new_status(
+                "A file with this name already exists in the folder",
+                StatusCode::CONFLICT,
+            ),
+            _ => StatusError::create(format!("Failed renaming the photo: {e}")),
+        })?;
+
+    if let Err(e) = state.photos_repo.update_photo(&renamed_photo).await {
+        // Put the file back where the database expects it
+        let _ = storage.rename_photo(&destination_path, &source_path);
+        return Err(e);
+    }
+
+    // Regenerated on the next request
+    let _ = fs::remove_file(storage.resolve_preview(photo.partial_preview_path())).await;
+
+    Ok(Json(renamed_photo))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(tag = "operation", rename_all = "camelCase")]
+enum BatchOperation {
+    Delete,
+    #[serde(rename_all = "camelCase")]
+    Move {
+        target_user_name: Option<String>,
+        target_folder_name: Option<String>,
+    },
+    Favorite,
+    Unfavorite,
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchBody {
+    photo_ids: Vec<i64>,
+    #[serde(flatten)]
+    operation: BatchOperation,
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct BatchResult {
+    photo_id: i64,
+    success: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    error: Option<String>,
+}
+
+impl BatchResult {
+    fn success(photo_id: i64) -> Self {
+        Self {
+            photo_id,
+            success: true,
+            error: None,
+        }
+    }
+
+    fn error<S: Into<String>>(photo_id: i64, error: S) -> Self {
+        Self {
+            photo_id,
+            success: false,
+            error: Some(error.into()),
+        }
+    }
+}
+
+///
+/// Returns the photos the user has the permission for, or owns when `permission` is `None`,
+/// and the error results of the other ones.
+///
+async fn get_accessible_photos(
+    state: &AppState,
+    user: &User,
+    photo_ids: &[i64],
+    permission: Option<SharePermission>,
+) -> AxumResult<(Vec<Photo>, Vec<BatchResult>)> {
+    let photos = state.photos_repo.get_photos(photo_ids).await?;
+    let mut results = Vec::with_capacity(photo_ids.len());
+
+    let found_ids = photos.iter().map(|photo| photo.id).collect::<HashSet<_>>();
+    for photo_id in photo_ids {
+        if !found_ids.contains(photo_id) {
+            results.push(BatchResult::error(*photo_id, "Photo not found"));
+        }
+    }
+
+    let mut accessible_photos = Vec::with_capacity(photos.len());
+    for photo in photos {
+        let access = match permission {
+            None => check_is_owner(Some(user.clone()), &photo),
+            Some(permission) => {
+                check_has_access(state, Some(user.clone()), &photo, permission).await
+            }
+        };
+
+        match access {
+            Ok(_) => accessible_photos.push(photo),
+            Err(_) => results.push(BatchResult::error(
+                photo.id,
+                "You don't have access to this resource",
+            )),
+        }
+    }
+
+    Ok((accessible_photos, results))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct ChangeDateBody {
+    photo_ids: Vec<i64>,
+    /// The new creation date of all the photos
+    #[serde(default, with = "timestamp::option")]
+    created_at: Option<OffsetDateTime>,
+    /// Moves the creation date of each photo by this many hours instead
+    shift_hours: Option<i64>,
+    /// Also replace the dates in the EXIF of the photos, only JPEGs with a date are supported
+    #[serde(default)]
+    write_exif: bool,
+}
+
+///
+/// Sets the creation date of multiple photos, or shifts it, in a single transaction.
+/// Returns the result for each photo.
+///
+async fn change_photos_date(
+    State(state): State<AppState>,
+    auth: AuthSession,
+    Json(body): Json<ChangeDateBody>,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if body.created_at.is_some() == body.shift_hours.is_some() {
+        return Err(StatusError::new_status(
+            "Either createdAt or shiftHours must be given",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    let new_date = |photo: &Photo| match body.created_at {
+        Some(created_at) => Some(created_at),
+        None => photo
+            .created_at
+            .checked_add(time::Duration::hours(body.shift_hours.unwrap_or_default())),
+    };
+
+    let (photos, mut results) = get_accessible_photos(
+        &state,
+        &user,
+        &body.photo_ids,
+        Some(SharePermission::Contribute),
+    )
+    .await?;
+
+    let mut changed_photos = Vec::with_capacity(photos.len());
+    for photo in photos {
+        let Some(created_at) = new_date(&photo) else {
+            results.push(BatchResult::error(
+                photo.id,
+                "The shifted date is out of range",
+            ));
+            continue;
+        };
+        if body.write_exif {
+            let path = state.storage.resolve_photo(photo.partial_path());
+            let checked = task::spawn_blocking(move || exif_date::check_exif_date(path))
+                .await
+                .map_err(internal_error)?;
+
+            if let Err(e) = checked {
+                results.push(BatchResult::error(
+                    photo.id,
+                    format!("Failed writing the EXIF date: {e}"),
+                ));
+                continue;
+            }
+        }
+
+        changed_photos.push(Photo {
+            created_at,
+            ..photo
+        });
+    }
+
+    // The files are only rewritten once the database has the new dates, so that a failed
+    // transaction never leaves them out of sync
+    state.photos_repo.update_photos(&changed_photos).await?;
+
+    if !body.write_exif {
+        results.extend(
+            changed_photos
+                .iter()
+                .map(|photo| BatchResult::success(photo.id)),
+        );
+        return Ok(Json(results));
+    }
+
+    let mut rewritten_photos = Vec::with_capacity(changed_photos.len());
+    for mut photo in changed_photos {
+        let path = state.storage.resolve_photo(photo.partial_path());
+        let created_at = photo.created_at;
+        let written = task::spawn_blocking(move || {
+            exif_date::write_exif_date(&path, created_at)?;
+            content_hash::hash_file(&path)
+        })
+        .await
+        .map_err(internal_error)?;
+
+        match written {
+            // The content changed along with the EXIF
+            Ok(hash) => {
+                photo.content_hash = Some(hash);
+                results.push(BatchResult::success(photo.id));
+                rewritten_photos.push(photo);
+            }
+            Err(e) => results.push(BatchResult::error(
+                photo.id,
+                format!("The date was changed but writing it in the EXIF failed: {e}"),
+            )),
+        }
+    }
+
+    state.photos_repo.update_photos(&rewritten_photos).await?;
+
+    Ok(Json(results))
+}
+
+///
+/// Applies the same operation to multiple photos, the database changes are made in a single
+/// transaction. Returns the result of the operation for each photo.
+///
+async fn batch_photos(
+    State(state): State<AppState>,
+    auth: AuthSession,
+    Json(body): Json<BatchBody>,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if let BatchOperation::Move {
+        target_folder_name: Some(folder_name),
+        ..
+    } = &body.operation
+    {
+        check_folder_name(folder_name)?;
+    }
+
+    let permission = match body.operation {
+        BatchOperation::Move { .. } => None,
+        BatchOperation::Delete => Some(SharePermission::Contribute),
+        BatchOperation::Favorite | BatchOperation::Unfavorite => Some(SharePermission::Read),
+    };
+    let (photos, mut results) =
+        get_accessible_photos(&state, &user, &body.photo_ids, permission).await?;
+
+    let photo_ids = photos.iter().map(|photo| photo.id).collect::<Vec<_>>();
+
+    match body.operation {
+        BatchOperation::Delete => {
+            results.extend(trash::trash_photos(&state, photos).await?.into_iter().map(
+                |(photo_id, result)| match result {
+                    Ok(_) => BatchResult::success(photo_id),
+                    Err(e) => BatchResult::error(photo_id, e),
+                },
+            ));
+        }
+        BatchOperation::Move {
+            target_user_name,
+            target_folder_name,
+        } => {
+            let target_user_name = target_user_name.unwrap_or(String::from(PUBLIC_USER_ID));
+            let mut taken_names =
+                trashed_names(&state, &target_user_name, target_folder_name.as_deref()).await?;
+            let mut moved_photos = Vec::with_capacity(photos.len());
+
+            for photo in photos {
+                let mut changed_photo = Photo {
+                    user_id: target_user_name.clone(),
+                    folder: target_folder_name.clone(),
+                    ..photo.clone()
+                };
+
+                let source_path = photo.partial_path();
+                if changed_photo.partial_path() == source_path {
+                    results.push(BatchResult::success(photo.id));
+                    continue;
+                }
+
+                // Never overwrite a photo already in the target folder, including one moved
+                // earlier in this batch
+                changed_photo.name = state
+                    .storage
+                    .available_name_excluding(changed_photo.partial_path(), &taken_names);
+                taken_names.insert(changed_photo.name.clone());
+                let destination_path = changed_photo.partial_path();
+
+                match state.storage.move_photo(&source_path, &destination_path) {
+                    Ok(_) => moved_photos.push((source_path, changed_photo)),
+                    Err(e) => results.push(BatchResult::error(
+                        photo.id,
+                        format!("Failed moving the photo: {e}"),
+                    )),
+                }
+            }
+
+            let changed_photos = moved_photos
+                .iter()
+                .map(|(_, photo)| photo.clone())
+                .collect::<Vec<_>>();
+
+            if let Err(e) = state.photos_repo.update_photos(&changed_photos).await {
+                // Put the files back where the database expects them
+                for (source_path, photo) in &moved_photos {
+                    let _ = state.storage.move_photo(photo.partial_path(), source_path);
+                }
+                return Err(e);
+            }
+
+            results.extend(
+                changed_photos
+                    .iter()
+                    .map(|photo| BatchResult::success(photo.id)),
+            );
+        }
+        BatchOperation::Favorite => {
+            state
+                .photos_repo
+                .insert_favorites(&photo_ids, &user.id)
+                .await?;
+            results.extend(photo_ids.into_iter().map(BatchResult::success));
+        }
+        BatchOperation::Unfavorite => {
+            state
+                .photos_repo
+                .delete_favorites(&photo_ids, &user.id)
+                .await?;
+            results.extend(photo_ids.into_iter().map(BatchResult::success));
+        }
+    }
+
+    Ok(Json(results))
+}
+
+/// Returns the comments on the photo, oldest first
+async fn get_comments(
+    State(state): State<AppState>,
+    Path(photo_id): Path<i64>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let photo = state.photos_repo.get_photo(photo_id).await?;
+    check_has_access(&state, auth.user, &photo, SharePermission::Read).await?;
+
+    Ok(Json(state.comments_repo.get_comments(photo.id).await?))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CommentBody {
+    text: String,
+}
+
+fn validate_comment(text: &str) -> AxumResult<&str> {
+    let text = text.trim();
+
+    if text.is_empty() {
+        return Err(StatusError::new_status(
+            "The comment can't be empty",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    if text.chars().count() > MAX_COMMENT_LENGTH {
+        return Err(StatusError::new_status(
+            format!("The comment can't be longer than {MAX_COMMENT_LENGTH} characters"),
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    Ok(text)
+}
+
+async fn add_comment(
+    State(state): State<AppState>,
+    Path(photo_id): Path<i64>,
+    auth: AuthSession,
+    Json(body): Json<CommentBody>,
+) -> AxumResult<impl IntoResponse> {
+    let photo = state.photos_repo.get_photo(photo_id).await?;
+    let user = check_has_access(&state, auth.user, &photo, SharePermission::Read).await?;
+    let text = validate_comment(&body.text)?;
+
+    let comment = state
+        .comments_repo
+        .insert_comment(photo.id, &user.id, text)
+        .await?;
+
+    Ok((StatusCode::CREATED, Json(comment)))
+}
+
+///
+/// Fetches the comment on the photo making sure the user still has access to the photo
+/// and is the author of the comment
+///
+async fn get_own_comment(
+    state: &AppState,
+    user: Option<User>,
+    photo_id: i64,
+    comment_id: i64,
+) -> AxumResult<PhotoComment> {
+    let photo = state.photos_repo.get_photo(photo_id).await?;
+    let user = check_has_access(state, user, &photo, SharePermission::Read).await?;
+
+    let comment = state
+        .comments_repo
+        .get_comment(comment_id)
+        .await?
+        .filter(|comment| comment.photo_id == photo.id)
+        .ok_or_else(|| StatusError::new_status("Comment not found", StatusCode::NOT_FOUND))?;
+
+    if comment.user_id != user.id {
+        return Err(StatusError::new_status(
+            "Only the author can change a comment",
+            StatusCode::FORBIDDEN,
+        ));
+    }
+
+    Ok(comment)
+}
+
+async fn edit_comment(
+    State(state): State<AppState>,
+    Path((photo_id, comment_id)): Path<(i64, i64)>,
+    auth: AuthSession,
+    Json(body): Json<CommentBody>,
+) -> AxumResult<impl IntoResponse> {
+    let comment = get_own_comment(&state, auth.user, photo_id, comment_id).await?;
+    let text = validate_comment(&body.text)?;
+
+    Ok(Json(
+        state.comments_repo.update_comment(comment.id, text).await?,
+    ))
+}
+
+async fn delete_comment(
+    State(state): State<AppState>,
+    Path((photo_id, comment_id)): Path<(i64, i64)>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let comment = get_own_comment(&state, auth.user, photo_id, comment_id).await?;
+
+    state.comments_repo.delete_comment(comment.id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Returns the ratings and color labels the user gave to the photos
+async fn get_ratings(
+    State(state): State<AppState>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    Ok(Json(state.ratings_repo.get_ratings(&user.id).await?))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct RatePhotosBody {
+    photo_ids: Vec<i64>,
+    /// From 0, which removes the rating, to [MAX_RATING] stars
+    rating: i64,
+}
+
+/// Sets the rating the user gives to multiple photos, returning the result for each photo
+async fn rate_photos(
+    State(state): State<AppState>,
+    auth: AuthSession,
+    Json(body): Json<RatePhotosBody>,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if !(0..=MAX_RATING).contains(&body.rating) {
+        return Err(StatusError::new_status(
+            format!("The rating must be between 0 and {MAX_RATING}"),
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+
+    let (photos, mut results) =
+        get_accessible_photos(&state, &user, &body.photo_ids, Some(SharePermission::Read)).await?;
+    let photo_ids = photos.iter().map(|photo| photo.id).collect::<Vec<_>>();
+
+    state
+        .ratings_repo
+        .set_ratings(&user.id, &photo_ids, body.rating)
+        .await?;
+    results.extend(photo_ids.into_iter().map(BatchResult::success));
+
+    Ok(Json(results))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct LabelPhotosBody {
+    photo_ids: Vec<i64>,
+    /// Removes the color label when missing
+    color_label: Option<ColorLabel>,
+}
+
+/// Sets the color label the user gives to multiple photos, returning the result for each photo
+async fn label_photos(
+    State(state): State<AppState>,
+    auth: AuthSession,
+    Json(body): Json<LabelPhotosBody>,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let (photos, mut results) =
+        get_accessible_photos(&state, &user, &body.photo_ids, Some(SharePermission::Read)).await?;
+    let photo_ids = photos.iter().map(|photo| photo.id).collect::<Vec<_>>();
+
+    state
+        .ratings_repo
+        .set_color_labels(&user.id, &photo_ids, body.color_label)
+        .await?;
+    results.extend(photo_ids.into_iter().map(BatchResult::success));
+
+    Ok(Json(results))
+}
+
 async fn get_favorites(
     State(state): State<AppState>,
     auth_session: AuthSession,
@@ -293,7 +1601,7 @@ async fn add_favorite(
     auth_session: AuthSession,
 ) -> AxumResult<impl IntoResponse> {
     let photo = state.photos_repo.get_photo(photo_id).await?;
-    let user = check_has_access(auth_session.user, &photo)?;
+    let user = check_has_access(&state, auth_session.user, &photo, SharePermission::Read).await?;
 
     state.photos_repo.insert_favorite(photo_id, user.id).await
 }
@@ -304,7 +1612,75 @@ async fn delete_favorite(
     auth_session: AuthSession,
 ) -> AxumResult<impl IntoResponse> {
     let photo = state.photos_repo.get_photo(photo_id).await?;
-    let user = check_has_access(auth_session.user, &photo)?;
+    let user = check_has_access(&state, auth_session.user, &photo, SharePermission::Read).await?;
 
     state.photos_repo.delete_favorite(photo_id, user.id).await
 }
+
+async fn get_trash(
+    State(state): State<AppState>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    Ok(Json(state.photos_repo.get_trashed_photos(user.id).await?))
+}
+
+/// Fetches a photo from the trash, making sure the user has access to it
+async fn get_trashed_photo(
+    state: &AppState,
+    auth: AuthSession,
+    photo_id: i64,
+) -> AxumResult<Photo> {
+    let photo = state
+        .photos_repo
+        .get_photo_including_trashed(photo_id)
+        .await?;
+    check_is_owner(auth.user, &photo)?;
+
+    if !photo.is_trashed() {
+        return Err(StatusError::new_status(
+            "Photo is not in the trash",
+            StatusCode::NOT_FOUND,
+        ));
+    }
+
+    Ok(photo)
+}
+
+async fn restore_photo(
+    State(state): State<AppState>,
+    Path(photo_id): Path<i64>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let photo = get_trashed_photo(&state, auth, photo_id).await?;
+
+    info!("Restoring photo {} from the trash", photo.id);
+
+    Ok(Json(trash::restore_photo(&state, photo).await?))
+}
+
+async fn delete_photo_permanently(
+    State(state): State<AppState>,
+    Path(photo_id): Path<i64>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let photo = get_trashed_photo(&state, auth, photo_id).await?;
+
+    trash::delete_permanently(&state, &[photo]).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+/// Permanently deletes all the trashed photos owned by the user
+async fn empty_trash(
+    State(state): State<AppState>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let photos = state.photos_repo.get_user_trashed_photos(&user.id).await?;
+    trash::delete_permanently(&state, &photos).await?;
+
+    Ok(Json(photos.len()))
+}
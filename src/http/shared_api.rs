@@ -0,0 +1,191 @@
+//!
+//! Routes for the people who received a share link, which don't require an account.
+//! Links with a password have to be unlocked first, which is remembered in the session.
+//!
+
+use axum::extract::{Path, Query, State};
+use axum::http::{HeaderMap, StatusCode};
+use axum::response::IntoResponse;
+use axum::routing::{get, post};
+use axum::{Json, Router};
+use axum_login::tower_sessions::Session;
+use serde::Serialize;
+use time::serde::timestamp;
+use time::OffsetDateTime;
+use tokio::task;
+
+use crate::http::utils::status_error::StatusError;
+use crate::http::utils::{
+    file_to_response, preview_to_response, AxumResult, DownloadQuery, PreviewQuery, NO_CACHE,
+};
+use crate::http::AppState;
+use crate::model::photo::{Photo, PhotoBase};
+use crate::model::share_link::ShareLink;
+use crate::utils::internal_error;
+use crate::utils::password_hash::validate_credentials;
+
+/// Session key of the ids of the links unlocked with their password
+const UNLOCKED_LINKS_KEY: &str = "unlocked_share_links";
+
+pub fn router(app_state: AppState) -> Router {
+    Router::new()
+        .route("/{token}", get(shared_photos))
+        .route("/{token}/unlock", post(unlock_link))
+        .route("/{token}/preview/{photo_id}", get(shared_preview))
+        .route("/{token}/download/{photo_id}", get(shared_download))
+        .with_state(app_state)
+}
+
+/// Fetches the link by its token, expired links are treated as if they don't exist
+async fn get_link(state: &AppState, token: &str) -> AxumResult<ShareLink> {
+    state
+        .share_links_repo
+        .get_link_by_token(token)
+        .await?
+        .filter(|link| !link.is_expired())
+        .ok_or_else(|| StatusError::new_status("Link not found", StatusCode::NOT_FOUND))
+}
+
+/// Fetches the link making sure it was unlocked if it has a password
+async fn get_unlocked_link(
+    state: &AppState,
+    session: &Session,
+    token: &str,
+) -> AxumResult<ShareLink> {
+    let link = get_link(state, token).await?;
+
+    if link.password_hash.is_some() {
+        let unlocked_links: Vec<i64> = session
+            .get(UNLOCKED_LINKS_KEY)
+            .await
+            .map_err(internal_error)?
+            .unwrap_or_default();
+
+        if !unlocked_links.contains(&link.id) {
+            return Err(StatusError::new_status(
+                "This link requires a password",
+                StatusCode::UNAUTHORIZED,
+            ));
+        }
+    }
+
+    Ok(link)
+}
+
+async fn get_link_photo(state: &AppState, link: &ShareLink, photo_id: i64) -> AxumResult<Photo> {
+    state
+        .share_links_repo
+        .get_link_photo(link, photo_id)
+        .await?
+        .ok_or_else(|| StatusError::new_status("Photo not found", StatusCode::NOT_FOUND))
+}
+
+#[derive(Debug, Serialize)]
+#[serde(rename_all = "camelCase")]
+struct SharedPhotos {
+    photos: Vec<Photo>,
+    allow_download: bool,
+    #[serde(with = "timestamp::option")]
+    expires_at: Option<OffsetDateTime>,
+}
+
+async fn shared_photos(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    session: Session,
+) -> AxumResult<impl IntoResponse> {
+    let link = get_unlocked_link(&state, &session, &token).await?;
+
+    Ok(Json(SharedPhotos {
+        photos: state.share_links_repo.get_link_photos(&link).await?,
+        allow_download: link.allow_download,
+        expires_at: link.expires_at,
+    }))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct UnlockBody {
+    password: String,
+}
+
+async fn unlock_link(
+    State(state): State<AppState>,
+    Path(token): Path<String>,
+    session: Session,
+    Json(body): Json<UnlockBody>,
+) -> AxumResult<impl IntoResponse> {
+    let link = get_link(&state, &token).await?;
+
+    let Some(password_hash) = link.password_hash else {
+        return Ok(StatusCode::NO_CONTENT);
+    };
+
+    let valid = task::spawn_blocking(move || validate_credentials(body.password, password_hash))
+        .await
+        .map_err(internal_error)?
+        .map_err(internal_error)?;
+
+    if !valid {
+        return Err(StatusError::new_status(
+            "Wrong password",
+            StatusCode::UNAUTHORIZED,
+        ));
+    }
+
+    let mut unlocked_links: Vec<i64> = session
+        .get(UNLOCKED_LINKS_KEY)
+        .await
+        .map_err(internal_error)?
+        .unwrap_or_default();
+    if !unlocked_links.contains(&link.id) {
+        unlocked_links.push(link.id);
+        session
+            .insert(UNLOCKED_LINKS_KEY, unlocked_links)
+            .await
+            .map_err(internal_error)?;
+    }
+
+    Ok(StatusCode::NO_CONTENT)
+}
+
+async fn shared_preview(
+    State(state): State<AppState>,
+    Path((token, photo_id)): Path<(String, i64)>,
+    Query(query): Query<PreviewQuery>,
+    headers: HeaderMap,
+    session: Session,
+) -> AxumResult<impl IntoResponse> {
+    let link = get_unlocked_link(&state, &session, &token).await?;
+    let photo = get_link_photo(&state, &link, photo_id).await?;
+
+    // Without a preview, the original is only served if the link allows downloading it
+    preview_to_response(
+        &state.storage,
+        &photo,
+        &headers,
+        &query,
+        link.allow_download,
+    )
+    .await
+}
+
+async fn shared_download(
+    State(state): State<AppState>,
+    Path((token, photo_id)): Path<(String, i64)>,
+    Query(query): Query<DownloadQuery>,
+    headers: HeaderMap,
+    session: Session,
+) -> AxumResult<impl IntoResponse> {
+    let link = get_unlocked_link(&state, &session, &token).await?;
+    if !link.allow_download {
+        return Err(StatusError::new_status(
+            "Downloads are not allowed for this link",
+            StatusCode::FORBIDDEN,
+        ));
+    }
+    let photo = get_link_photo(&state, &link, photo_id).await?;
+
+    let photo_path = state.storage.resolve_photo(photo.partial_path());
+    file_to_response(&photo_path, &headers, query.disposition(), NO_CACHE).await
+}
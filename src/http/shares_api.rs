@@ -0,0 +1,138 @@
+use axum::extract::{Path, State};
+use axum::http::StatusCode;
+use axum::response::IntoResponse;
+use axum::routing::{delete, get};
+use axum::{Json, Router};
+
+use crate::http::utils::status_error::StatusError;
+use crate::http::utils::{check_folder_name, AuthSession, AxumResult};
+use crate::http::AppState;
+use crate::model::photo_share::SharePermission;
+use crate::model::user::PUBLIC_USER_ID;
+
+pub fn router(app_state: AppState) -> Router {
+    Router::new()
+        .route("/", get(list_shares).post(create_share))
+        .route("/received", get(list_received_shares))
+        .route("/{share_id}", delete(delete_share))
+        .with_state(app_state)
+}
+
+async fn list_shares(
+    State(state): State<AppState>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    Ok(Json(state.shares_repo.get_shares_by_owner(user.id).await?))
+}
+
+async fn list_received_shares(
+    State(state): State<AppState>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    Ok(Json(state.shares_repo.get_shares_with_user(user.id).await?))
+}
+
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+struct CreateShareBody {
+    /// The user to share with
+    user_id: String,
+    photo_id: Option<i64>,
+    folder: Option<String>,
+    permission: SharePermission,
+}
+
+/// Shares one of the user's photos or folders along with its subfolders, only one of the two must be given
+async fn create_share(
+    State(state): State<AppState>,
+    auth: AuthSession,
+    Json(body): Json<CreateShareBody>,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if body.user_id == user.id || body.user_id == PUBLIC_USER_ID {
+        return Err(StatusError::new_status(
+            "Photos can only be shared with other users",
+            StatusCode::BAD_REQUEST,
+        ));
+    }
+    if state.users_repo.get_user(&body.user_id).await.is_none() {
+        return Err(StatusError::new_status(
+            "User not found",
+            StatusCode::NOT_FOUND,
+        ));
+    }
+
+    let share = match (body.photo_id, body.folder) {
+        (Some(photo_id), None) => {
+            let photo = state.photos_repo.get_photo(photo_id).await?;
+            if photo.user_id != user.id {
+                return Err(StatusError::new_status(
+                    "Only your own photos can be shared",
+                    StatusCode::FORBIDDEN,
+                ));
+            }
+
+            state
+                .shares_repo
+                .share_photo(user.id, body.user_id, photo.id, body.permission)
+                .await?
+        }
+        (None, Some(folder)) if !folder.is_empty() => {
+            check_folder_name(&folder)?;
+            let has_directory = state
+                .storage
+                .resolve_photo(format!("{}/{folder}", user.id))
+                .is_dir();
+            if !has_directory
+                && state
+                    .folders_repo
+                    .get_folder_tree_photos(&user.id, &folder)
+                    .await?
+                    .is_empty()
+            {
+                return Err(StatusError::new_status(
+                    "Folder not found",
+                    StatusCode::NOT_FOUND,
+                ));
+            }
+
+            state
+                .shares_repo
+                .share_folder(user.id, body.user_id, folder, body.permission)
+                .await?
+        }
+        _ => {
+            return Err(StatusError::new_status(
+                "Either a photo or a folder must be shared",
+                StatusCode::BAD_REQUEST,
+            ))
+        }
+    };
+
+    Ok((StatusCode::CREATED, Json(share)))
+}
+
+/// Revokes a share, which can be done both by its owner and by the user it was shared with
+async fn delete_share(
+    State(state): State<AppState>,
+    Path(share_id): Path<i64>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    let share = state
+        .shares_repo
+        .get_share(share_id)
+        .await?
+        .filter(|share| share.owner_id == user.id || share.user_id == user.id)
+        .ok_or_else(|| StatusError::new_status("Share not found", StatusCode::NOT_FOUND))?;
+
+    state.shares_repo.delete_share(share.id).await?;
+
+    Ok(StatusCode::NO_CONTENT)
+}
@@ -0,0 +1,380 @@
+//!
+//! Resumable uploads following the tus 1.0 protocol (https://tus.io/protocols/resumable-upload)
+//! with the creation and termination extensions.
+//!
+//! Once all the bytes have been received, the client has to call `POST /uploads/{id}/finalize`
+//! which moves the file into the storage and returns the newly created photo,
+//! or the existing one if the same file was already uploaded.
+//!
+
+use std::collections::{HashMap, HashSet};
+use std::io::SeekFrom;
+use std::sync::{Arc, Mutex};
+
+use axum::body::Body;
+use axum::extract::{Path, State};
+use axum::http::{header, HeaderMap, HeaderName, StatusCode};
+use axum::response::{IntoResponse, Response};
+use axum::routing::{head, post};
+use axum::{Json, Router};
+use base64::prelude::{Engine, BASE64_STANDARD};
+use futures_util::TryStreamExt;
+use rand::distributions::{Alphanumeric, DistString};
+use time::OffsetDateTime;
+use tokio::io::{AsyncSeekExt, AsyncWriteExt};
+use tokio::{fs, task};
+use tracing::{error, info};
+
+use crate::http::utils::status_error::StatusError;
+use crate::http::utils::{check_folder_name, save_staged_photo, AuthSession, AxumResult};
+use crate::http::AppState;
+use crate::model::upload::Upload;
+use crate::utils::content_hash::hash_file;
+use crate::utils::{file_name, internal_error};
+
+const TUS_VERSION: &str = "1.0.0";
+
+const TUS_RESUMABLE: HeaderName = HeaderName::from_static("tus-resumable");
+const UPLOAD_LENGTH: HeaderName = HeaderName::from_static("upload-length");
+const UPLOAD_OFFSET: HeaderName = HeaderName::from_static("upload-offset");
+const UPLOAD_METADATA: HeaderName = HeaderName::from_static("upload-metadata");
+const TUS_MAX_SIZE: HeaderName = HeaderName::from_static("tus-max-size");
+
+/// Largest file accepted, 64 GiB
+const MAX_UPLOAD_LENGTH: i64 = 64 * 1024 * 1024 * 1024;
+
+pub fn router(app_state: AppState) -> Router {
+    Router::new()
+        .route("/", post(create_upload))
+        .route(
+            "/{upload_id}",
+            head(upload_offset)
+                .patch(append_to_upload)
+                .delete(delete_upload),
+        )
+        .route("/{upload_id}/finalize", post(finalize_upload))
+        .with_state(app_state)
+}
+
+/// The uploads a request is currently writing to, so that concurrent requests never touch the same file
+#[derive(Clone, Default)]
+pub struct ActiveUploads(Arc<Mutex<HashSet<String>>>);
+
+impl ActiveUploads {
+    /// Marks the upload as active until the guard is dropped, fails with 423 if it already is
+    fn lock(&self, upload_id: &str) -> AxumResult<ActiveUploadGuard> {
+        if self.0.lock().unwrap().insert(upload_id.to_string()) {
+            Ok(ActiveUploadGuard {
+                active_uploads: self.clone(),
+                upload_id: upload_id.to_string(),
+            })
+        } else {
+            Err(StatusError::new_status(
+                "The upload is being written to by another request",
+                StatusCode::LOCKED,
+            ))
+        }
+    }
+}
+
+struct ActiveUploadGuard {
+    active_uploads: ActiveUploads,
+    upload_id: String,
+}
+
+impl Drop for ActiveUploadGuard {
+    fn drop(&mut self) {
+        self.active_uploads
+            .0
+            .lock()
+            .unwrap()
+            .remove(&self.upload_id);
+    }
+}
+
+fn check_tus_version(headers: &HeaderMap) -> AxumResult<()> {
+    match headers.get(TUS_RESUMABLE) {
+        Some(version) if version != TUS_VERSION => Err(StatusError::new_status(
+            format!("Only version {TUS_VERSION} of the tus protocol is supported"),
+            StatusCode::PRECONDITION_FAILED,
+        )),
+        _ => Ok(()),
+    }
+}
+
+fn header_i64(headers: &HeaderMap, name: HeaderName) -> AxumResult<i64> {
+    headers
+        .get(&name)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| value.parse::<i64>().ok())
+        .filter(|value| *value >= 0)
+        .ok_or_else(|| {
+            StatusError::new_status(
+                format!("Missing or invalid {name} header"),
+                StatusCode::BAD_REQUEST,
+            )
+        })
+}
+
+///
+/// Decodes the `Upload-Metadata` header: comma separated pairs of a key and
+/// an optional base64 encoded value, separated by a space
+///
+fn parse_metadata(value: &str) -> Option<HashMap<String, String>> {
+    value
+        .split(',')
+        .map(str::trim)
+        .filter(|pair| !pair.is_empty())
+        .map(|pair| {
+            let (key, value) = pair.split_once(' ').unwrap_or((pair, ""));
+            let value = BASE64_STANDARD.decode(value.trim()).ok()?;
+
+            Some((key.to_string(), String::from_utf8(value).ok()?))
+        })
+        .collect()
+}
+
+/// Fetches the upload making sure it belongs to the current user
+async fn get_user_upload(
+    state: &AppState,
+    auth: AuthSession,
+    upload_id: &str,
+) -> AxumResult<Upload> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    state
+        .uploads_repo
+        .get_upload(upload_id)
+        .await?
+        .filter(|upload| upload.user_id == user.id)
+        .ok_or_else(|| StatusError::new_status("Upload not found", StatusCode::NOT_FOUND))
+}
+
+async fn create_upload(
+    State(state): State<AppState>,
+    headers: HeaderMap,
+    auth: AuthSession,
+) -> AxumResult<Response> {
+    let user = auth.user.ok_or(StatusCode::UNAUTHORIZED)?;
+    check_tus_version(&headers)?;
+
+    let upload_length = header_i64(&headers, UPLOAD_LENGTH)?;
+    if upload_length > MAX_UPLOAD_LENGTH {
+        return Ok((
+            StatusCode::PAYLOAD_TOO_LARGE,
+            [
+                (TUS_MAX_SIZE, MAX_UPLOAD_LENGTH.to_string()),
+                (TUS_RESUMABLE, TUS_VERSION.to_string()),
+            ],
+            "Upload-Length exceeds Tus-Max-Size",
+        )
+            .into_response());
+    }
+    let metadata = headers
+        .get(UPLOAD_METADATA)
+        .and_then(|value| value.to_str().ok())
+        .map(parse_metadata)
+        .unwrap_or_else(|| Some(HashMap::new()))
+        .ok_or_else(|| {
+            StatusError::new_status("Invalid Upload-Metadata header", StatusCode::BAD_REQUEST)
+        })?;
+
+    let name = metadata
+        .get("filename")
+        .filter(|name| !name.is_empty())
+        .ok_or_else(|| {
+            StatusError::new_status("Upload has no filename", StatusCode::BAD_REQUEST)
+        })?;
+    file_name::validate_name(name).map_err(|e| {
+        StatusError::new_status(format!("Invalid filename: {e}"), StatusCode::BAD_REQUEST)
+    })?;
+    if let Some(folder_name) = metadata.get("folderName") {
+        check_folder_name(folder_name)?;
+    }
+    let time_created = metadata
+        .get("timeCreated")
+        .and_then(|time| time.parse::<i64>().ok())
+        .and_then(|time| OffsetDateTime::from_unix_timestamp(time).ok())
+        .ok_or_else(|| {
+            StatusError::new_status("Upload has no valid timeCreated", StatusCode::BAD_REQUEST)
+        })?;
+
+    let now = OffsetDateTime::now_utc();
+    let upload = Upload {
+        id: Alphanumeric.sample_string(&mut rand::thread_rng(), 32),
+        user_id: user.id,
+        name: name.clone(),
+        folder: metadata.get("folderName").cloned(),
+        time_created,
+        make_public: metadata.contains_key("makePublic"),
+        upload_length,
+        upload_offset: 0,
+        started_at: now,
+        updated_at: now,
+    };
+
+    fs::File::create(state.storage.resolve_upload(&upload.id))
+        .await
+        .map_err(|e| {
+            error!("Failed creating upload file: {e}");
+            StatusError::create("Failed creating upload file")
+        })?;
+    state.uploads_repo.insert_upload(&upload).await?;
+
+    info!("Started upload {} of {}", upload.id, upload.name);
+
+    Ok((
+        StatusCode::CREATED,
+        [
+            (header::LOCATION, format!("/uploads/{}", upload.id)),
+            (TUS_MAX_SIZE, MAX_UPLOAD_LENGTH.to_string()),
+            (TUS_RESUMABLE, TUS_VERSION.to_string()),
+        ],
+    )
+        .into_response())
+}
+
+async fn upload_offset(
+    State(state): State<AppState>,
+    Path(upload_id): Path<String>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let upload = get_user_upload(&state, auth, &upload_id).await?;
+
+    Ok((
+        StatusCode::OK,
+        [
+            (UPLOAD_OFFSET, upload.upload_offset.to_string()),
+            (UPLOAD_LENGTH, upload.upload_length.to_string()),
+            (header::CACHE_CONTROL, "no-store".to_string()),
+            (TUS_RESUMABLE, TUS_VERSION.to_string()),
+        ],
+    ))
+}
+
+async fn append_to_upload(
+    State(state): State<AppState>,
+    Path(upload_id): Path<String>,
+    headers: HeaderMap,
+    auth: AuthSession,
+    body: Body,
+) -> AxumResult<impl IntoResponse> {
+    let _guard = state.active_uploads.lock(&upload_id)?;
+    let upload = get_user_upload(&state, auth, &upload_id).await?;
+    check_tus_version(&headers)?;
+
+    if headers
+        .get(header::CONTENT_TYPE)
+        .is_none_or(|content_type| content_type != "application/offset+octet-stream")
+    {
+        return Err(StatusError::new_status(
+            "Content-Type must be application/offset+octet-stream",
+            StatusCode::UNSUPPORTED_MEDIA_TYPE,
+        ));
+    }
+
+    let offset = header_i64(&headers, UPLOAD_OFFSET)?;
+    if offset != upload.upload_offset {
+        return Err(StatusError::new_status(
+            format!("Upload-Offset must be {}", upload.upload_offset),
+            StatusCode::CONFLICT,
+        ));
+    }
+
+    let mut file = fs::OpenOptions::new()
+        .write(true)
+        .open(state.storage.resolve_upload(&upload.id))
+        .await
+        .map_err(internal_error)?;
+    // Discard anything written after the last offset that was saved
+    file.set_len(offset as u64).await.map_err(internal_error)?;
+    file.seek(SeekFrom::Start(offset as u64))
+        .await
+        .map_err(internal_error)?;
+
+    let mut new_offset = offset;
+    let mut stream = body.into_data_stream();
+    let result: AxumResult<()> = async {
+        while let Some(chunk) = stream.try_next().await.map_err(internal_error)? {
+            if new_offset + chunk.len() as i64 > upload.upload_length {
+                return Err(StatusError::new_status(
+                    "Upload exceeds its Upload-Length",
+                    StatusCode::BAD_REQUEST,
+                ));
+            }
+
+            file.write_all(&chunk).await.map_err(internal_error)?;
+            new_offset += chunk.len() as i64;
+        }
+
+        Ok(())
+    }
+    .await;
+
+    // Whatever was received is kept even if the connection was interrupted,
+    // so that the client can resume from there
+    file.sync_data().await.map_err(internal_error)?;
+    state
+        .uploads_repo
+        .update_offset(&upload.id, new_offset)
+        .await?;
+    result?;
+
+    Ok((
+        StatusCode::NO_CONTENT,
+        [
+            (UPLOAD_OFFSET, new_offset.to_string()),
+            (TUS_RESUMABLE, TUS_VERSION.to_string()),
+        ],
+    ))
+}
+
+async fn delete_upload(
+    State(state): State<AppState>,
+    Path(upload_id): Path<String>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let _guard = state.active_uploads.lock(&upload_id)?;
+    let upload = get_user_upload(&state, auth, &upload_id).await?;
+
+    let _ = fs::remove_file(state.storage.resolve_upload(&upload.id)).await;
+    state.uploads_repo.delete_upload(&upload.id).await?;
+
+    Ok((StatusCode::NO_CONTENT, [(TUS_RESUMABLE, TUS_VERSION)]))
+}
+
+async fn finalize_upload(
+    State(state): State<AppState>,
+    Path(upload_id): Path<String>,
+    auth: AuthSession,
+) -> AxumResult<impl IntoResponse> {
+    let _guard = state.active_uploads.lock(&upload_id)?;
+    let upload = get_user_upload(&state, auth, &upload_id).await?;
+
+    if !upload.is_complete() {
+        return Err(StatusError::new_status(
+            format!(
+                "Upload is incomplete: {} out of {} bytes",
+                upload.upload_offset, upload.upload_length
+            ),
+            StatusCode::CONFLICT,
+        ));
+    }
+
+    let upload_path = state.storage.resolve_upload(&upload.id);
+    let hash_path = upload_path.clone();
+    let content_hash = task::spawn_blocking(move || hash_file(hash_path))
+        .await
+        .map_err(internal_error)?
+        .map_err(internal_error)?;
+
+    let mut photo_body = upload.photo_body();
+    photo_body.set_content_hash(content_hash);
+
+    info!("Finalizing upload {}", upload.id);
+
+    let photo = save_staged_photo(&state, &upload.user_id, &upload_path, photo_body).await?;
+    state.uploads_repo.delete_upload(&upload.id).await?;
+
+    Ok(Json(photo))
+}
@@ -1,16 +1,31 @@
+use std::collections::HashSet;
+use std::io::SeekFrom;
+
 use axum::body::Body;
 use axum::extract::multipart;
-use axum::http::header;
-use axum::response::IntoResponse;
+use axum::http::{header, HeaderMap, HeaderValue, StatusCode};
+use axum::response::{ErrorResponse, IntoResponse, Response};
 use futures_util::TryStreamExt;
-use tokio::fs;
-use tokio::io::AsyncWriteExt;
+use sha2::Digest;
+use time::format_description::BorrowedFormatItem;
+use time::macros::format_description;
+use time::{OffsetDateTime, PrimitiveDateTime};
+use tokio::io::{AsyncReadExt, AsyncSeekExt, AsyncWriteExt};
+use tokio::{fs, task};
 use tokio_util::io::ReaderStream;
-use tracing::error;
+use tracing::{error, info, warn};
 
 use crate::http::utils::status_error::StatusError;
+use crate::http::AppState;
+use crate::model::photo::{Photo, PhotoBase, PhotoBody};
+use crate::model::photo_share::SharePermission;
+use crate::model::user::{User, PUBLIC_USER_ID};
+use crate::previews;
 use crate::repo::users_repo::UsersRepository;
-use crate::utils::internal_error;
+use crate::utils::content_hash::{finalize_hash, ContentHasher};
+use crate::utils::storage_resolver::StorageResolver;
+use crate::utils::zip_stream::ZipWriter;
+use crate::utils::{file_name, internal_error, photo_metadata};
 
 pub mod status_error;
 
@@ -18,55 +33,588 @@ pub type AxumResult<T> = axum::response::Result<T>;
 
 pub type AuthSession = axum_login::AuthSession<UsersRepository>;
 
-pub async fn file_to_response(photo_path: &std::path::Path) -> AxumResult<impl IntoResponse + use<>> {
+/// How the client should present a file served by [file_to_response]
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Disposition {
+    /// Displayed directly by the client, ex: in an `<img>` or `<video>` tag
+    Inline,
+    /// Saved to disk by the client
+    Attachment,
+}
+
+/// Query of the routes serving the original photo
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct DownloadQuery {
+    /// Whether the photo should be displayed instead of saved by the client
+    #[serde(default)]
+    inline: bool,
+}
+
+impl DownloadQuery {
+    pub fn disposition(&self) -> Disposition {
+        if self.inline {
+            Disposition::Inline
+        } else {
+            Disposition::Attachment
+        }
+    }
+}
+
+/// Query of the routes serving a preview
+#[derive(Debug, serde::Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PreviewQuery {
+    /// The content hash of the photo, which makes the URL unique to this version of the photo
+    hash: Option<String>,
+}
+
+impl PreviewQuery {
+    /// Previews are only cached for long when their URL has the content hash of the photo,
+    /// as the id of a deleted photo can be given to a new one
+    pub fn cache_control(&self, photo: &Photo) -> &'static str {
+        match (&self.hash, photo.content_hash()) {
+            (Some(hash), Some(content_hash)) if hash == content_hash => LONG_CACHE,
+            _ => NO_CACHE,
+        }
+    }
+}
+
+/// Cache policy of the original photos, which must always be revalidated using their `ETag`
+pub const NO_CACHE: &str = "private, no-cache";
+
+/// Cache policy of the previews requested with the content hash of their photo
+const LONG_CACHE: &str = "private, max-age=31536000";
+
+const HTTP_DATE_FORMAT: &[BorrowedFormatItem] = format_description!(
+    "[weekday repr:short], [day] [month repr:short] [year] [hour]:[minute]:[second] GMT"
+);
+
+/// Serves the file with support for `Range`/`If-Range` requests and conditional requests
+/// using the `ETag` and `Last-Modified` headers, which are computed from the file's metadata
+pub async fn file_to_response(
+    photo_path: &std::path::Path,
+    request_headers: &HeaderMap,
+    disposition: Disposition,
+    cache_control: &'static str,
+) -> AxumResult<Response> {
+    let mut file = fs::File::open(&photo_path)
+        .await
+        .map_err(|e| StatusError::create(e.to_string()))?;
+    let metadata = file.metadata().await.map_err(internal_error)?;
+
+    let file_size = metadata.len();
+    let modified = metadata
+        .modified()
+        .map(OffsetDateTime::from)
+        .map_err(internal_error)?;
+    let etag = format!("\"{:x}-{:x}\"", file_size, modified.unix_timestamp_nanos());
+    let last_modified = modified
+        .format(HTTP_DATE_FORMAT)
+        .map_err(|e| StatusError::create(e.to_string()))?;
+
+    let mut headers = HeaderMap::new();
+    headers.insert(header::ETAG, header_value(&etag)?);
+    headers.insert(header::LAST_MODIFIED, header_value(&last_modified)?);
+    headers.insert(
+        header::CACHE_CONTROL,
+        HeaderValue::from_static(cache_control),
+    );
+
+    if is_not_modified(request_headers, &etag, modified) {
+        return Ok((StatusCode::NOT_MODIFIED, headers).into_response());
+    }
+
     let mime = mime_guess::from_path(photo_path)
         .first_or_octet_stream()
         .as_ref()
         .to_string();
+    let disposition = match disposition {
+        Disposition::Inline => "inline",
+        Disposition::Attachment => "attachment",
+    };
 
-    let stream = ReaderStream::new(
-        fs::File::open(&photo_path)
-            .await
-            .map_err(|e| StatusError::create(e.to_string()))?,
+    headers.insert(header::ACCEPT_RANGES, HeaderValue::from_static("bytes"));
+    headers.insert(header::CONTENT_TYPE, header_value(&mime)?);
+    headers.insert(
+        header::CONTENT_DISPOSITION,
+        header_value(&format!(
+            "{disposition}; filename=\"{}\"",
+            photo_path
+                .file_name()
+                .expect("Photo must have a name")
+                .to_string_lossy()
+        ))?,
     );
-    // convert the `Stream` into an `axum::body::HttpBody`
-    let body = Body::from_stream(stream);
+
+    // A range is only served if the file didn't change since the client got the first part
+    let if_range_matches = request_headers
+        .get(header::IF_RANGE)
+        .and_then(|value| value.to_str().ok())
+        .is_none_or(|value| value == etag || value == last_modified);
+
+    let range = request_headers
+        .get(header::RANGE)
+        .and_then(|value| value.to_str().ok())
+        .filter(|_| if_range_matches)
+        .and_then(|value| parse_range(value, file_size));
+
+    match range {
+        None => {
+            headers.insert(header::CONTENT_LENGTH, HeaderValue::from(file_size));
+            // convert the `Stream` into an `axum::body::HttpBody`
+            let body = Body::from_stream(ReaderStream::new(file));
+
+            Ok((StatusCode::OK, headers, body).into_response())
+        }
+        Some(Ok((start, end))) => {
+            file.seek(SeekFrom::Start(start))
+                .await
+                .map_err(internal_error)?;
+            let length = end - start + 1;
+
+            headers.insert(header::CONTENT_LENGTH, HeaderValue::from(length));
+            headers.insert(
+                header::CONTENT_RANGE,
+                header_value(&format!("bytes {start}-{end}/{file_size}"))?,
+            );
+            let body = Body::from_stream(ReaderStream::new(file.take(length)));
+
+            Ok((StatusCode::PARTIAL_CONTENT, headers, body).into_response())
+        }
+        Some(Err(())) => {
+            headers.insert(
+                header::CONTENT_RANGE,
+                header_value(&format!("bytes */{file_size}"))?,
+            );
+
+            Ok((StatusCode::RANGE_NOT_SATISFIABLE, headers).into_response())
+        }
+    }
+}
+
+/// Serves the preview of the photo, generating it first if needed.
+/// If the preview can't be generated, the original is served instead when `fallback_to_original`
+/// is set, otherwise the preview is not found.
+pub async fn preview_to_response(
+    storage: &StorageResolver,
+    photo: &Photo,
+    request_headers: &HeaderMap,
+    query: &PreviewQuery,
+    fallback_to_original: bool,
+) -> AxumResult<Response> {
+    let photo_path = if photo.is_trashed() {
+        storage.resolve_trash(photo.partial_trash_path())
+    } else {
+        storage.resolve_photo(photo.partial_path())
+    };
+    let preview_path = storage.resolve_preview(photo.partial_preview_path());
+
+    let preview_generated = if !preview_path.exists() {
+        let photo_path_clone = photo_path.clone();
+        let preview_path_clone = preview_path.clone();
+
+        task::spawn_blocking(move || {
+            previews::generate_preview(photo_path_clone, preview_path_clone)
+        })
+        .await
+        .unwrap()
+    } else {
+        Ok(())
+    };
+
+    let (path, cache_control) = match preview_generated {
+        Ok(_) => (preview_path, query.cache_control(photo)),
+        Err(e) => {
+            error!(
+                "Preview generation failed for video: {}\nCause: {e}",
+                photo_path.display()
+            );
+            if !fallback_to_original {
+                return Err(StatusError::new_status(
+                    "No preview is available for this photo",
+                    StatusCode::NOT_FOUND,
+                ));
+            }
+            (photo_path, NO_CACHE)
+        }
+    };
+
+    file_to_response(&path, request_headers, Disposition::Inline, cache_control).await
+}
+
+///
+/// Streams a store-only ZIP of the photos, built while it is being sent.
+/// The entries keep the folder of the photos and their creation time, a photo whose file
+/// can't be read is left out as the response has already started by then.
+///
+pub fn archive_to_response(
+    storage: StorageResolver,
+    photos: Vec<Photo>,
+    archive_name: &str,
+) -> AxumResult<Response> {
+    let (writer, reader) = tokio::io::duplex(64 * 1024);
+
+    task::spawn(async move {
+        let mut zip = ZipWriter::new(writer);
+        let mut used_names = HashSet::with_capacity(photos.len());
+
+        for photo in photos {
+            let file = match fs::File::open(storage.resolve_photo(photo.partial_path())).await {
+                Ok(file) => file,
+                Err(e) => {
+                    warn!("Leaving {} out of the archive: {e}", photo.partial_path());
+                    continue;
+                }
+            };
+
+            // Photos of different users may have the same name
+            let name = available_entry_name(&used_names, photo.full_name());
+            used_names.insert(name.clone());
+
+            if let Err(e) = zip.add_file(&name, photo.created_at(), file).await {
+                // Most likely the client disconnected
+                warn!("Archive streaming stopped: {e}");
+                return;
+            }
+        }
+
+        if let Err(e) = zip.finish().await {
+            warn!("Archive streaming stopped: {e}");
+        }
+    });
 
     let headers = [
-        (header::CONTENT_TYPE, mime),
+        (
+            header::CONTENT_TYPE,
+            HeaderValue::from_static("application/zip"),
+        ),
         (
             header::CONTENT_DISPOSITION,
-            format!(
-                "attachment; filename=\"{}\"",
-                photo_path
-                    .file_name()
-                    .expect("Photo must have a name")
-                    .to_string_lossy()
-            ),
+            header_value(&format!("attachment; filename=\"{archive_name}.zip\""))?,
         ),
     ];
 
-    Ok((headers, body))
+    Ok((headers, Body::from_stream(ReaderStream::new(reader))).into_response())
+}
+
+/// Appends " (n)" to the name if it is already used: "Trip/a (1).jpg"
+fn available_entry_name(used_names: &HashSet<String>, name: String) -> String {
+    if !used_names.contains(&name) {
+        return name;
+    }
+
+    let path = std::path::Path::new(&name);
+    let stem = path.with_extension("");
+    let stem = stem.to_string_lossy();
+    let extension = path
+        .extension()
+        .map(|extension| extension.to_string_lossy());
+
+    (1..)
+        .map(|copy_index| match &extension {
+            Some(extension) => format!("{stem} ({copy_index}).{extension}"),
+            None => format!("{stem} ({copy_index})"),
+        })
+        .find(|candidate| !used_names.contains(candidate))
+        .expect("There is always a free name")
+}
+
+fn header_value(value: &str) -> AxumResult<HeaderValue> {
+    HeaderValue::from_str(value).map_err(internal_error)
+}
+
+fn is_not_modified(request_headers: &HeaderMap, etag: &str, modified: OffsetDateTime) -> bool {
+    // If-Modified-Since must be ignored when If-None-Match is present
+    if let Some(if_none_match) = request_headers.get(header::IF_NONE_MATCH) {
+        return if_none_match.to_str().is_ok_and(|value| {
+            value
+                .split(',')
+                .map(|tag| tag.trim().trim_start_matches("W/"))
+                .any(|tag| tag == "*" || tag == etag)
+        });
+    }
+
+    request_headers
+        .get(header::IF_MODIFIED_SINCE)
+        .and_then(|value| value.to_str().ok())
+        .and_then(|value| PrimitiveDateTime::parse(value, HTTP_DATE_FORMAT).ok())
+        .is_some_and(|since| modified.unix_timestamp() <= since.assume_utc().unix_timestamp())
 }
 
 ///
-/// Returns the amount of bytes written to disk
+/// Parses a single `bytes` range into the inclusive `(start, end)` positions in the file.
+/// Returns `None` if the header should be ignored and `Some(Err)` if it can't be satisfied.
+///
+fn parse_range(range: &str, file_size: u64) -> Option<Result<(u64, u64), ()>> {
+    let range = range.trim().strip_prefix("bytes=")?;
+    // Serving multiple ranges is not supported, the whole file is served instead
+    if range.contains(',') {
+        return None;
+    }
+
+    let (start, end) = range.split_once('-')?;
+    let (start, end) = (start.trim(), end.trim());
+
+    if start.is_empty() {
+        // Suffix range: the last N bytes
+        let suffix: u64 = end.parse().ok()?;
+        if suffix == 0 || file_size == 0 {
+            return Some(Err(()));
+        }
+
+        return Some(Ok((file_size.saturating_sub(suffix), file_size - 1)));
+    }
+
+    let start: u64 = start.parse().ok()?;
+    let end = if end.is_empty() {
+        None
+    } else {
+        Some(end.parse::<u64>().ok()?)
+    };
+
+    if end.is_some_and(|end| end < start) {
+        return None;
+    }
+    if start >= file_size {
+        return Some(Err(()));
+    }
+
+    let end = end.map_or(file_size - 1, |end| end.min(file_size - 1));
+    Some(Ok((start, end)))
+}
+
+/// Makes sure the user is logged in and owns the photo or that it is public, returning the user
+pub fn check_is_owner(user: Option<User>, photo: &Photo) -> Result<User, ErrorResponse> {
+    let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if is_owner(&user, photo) {
+        Ok(user)
+    } else {
+        Err(forbidden())
+    }
+}
+
+///
+/// Makes sure the user is logged in and can access the photo, returning the user.
+/// Besides its own and the public photos, the user can access the photos shared with it
+/// with at least the given permission.
+///
+pub async fn check_has_access(
+    state: &AppState,
+    user: Option<User>,
+    photo: &Photo,
+    permission: SharePermission,
+) -> Result<User, ErrorResponse> {
+    let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if is_owner(&user, photo)
+        || state
+            .shares_repo
+            .get_photo_permission(&user.id, photo.id)
+            .await?
+            .is_some_and(|granted| granted >= permission)
+    {
+        Ok(user)
+    } else {
+        Err(forbidden())
+    }
+}
+
+///
+/// Makes sure the user is logged in and can access the folder of the owner, returning the user.
+/// Follows the same rules as [check_has_access], using the permission granted for the folder.
+///
+pub async fn check_folder_access(
+    state: &AppState,
+    user: Option<User>,
+    owner_id: &str,
+    folder: &str,
+    permission: SharePermission,
+) -> Result<User, ErrorResponse> {
+    let user = user.ok_or(StatusCode::UNAUTHORIZED)?;
+
+    if owner_id == user.id
+        || owner_id == PUBLIC_USER_ID
+        || state
+            .shares_repo
+            .get_folder_permission(&user.id, owner_id, folder)
+            .await?
+            .is_some_and(|granted| granted >= permission)
+    {
+        Ok(user)
+    } else {
+        Err(forbidden())
+    }
+}
+
+/// Rejects folders that are not a safe relative path in the storage with a bad request
+pub fn check_folder_name(folder: &str) -> AxumResult<()> {
+    file_name::validate_folder(folder).map_err(|e| {
+        StatusError::new_status(format!("Invalid folder: {e}"), StatusCode::BAD_REQUEST)
+    })
+}
+
+fn is_owner(user: &User, photo: &Photo) -> bool {
+    photo.user_id() == &user.id || photo.user_id() == PUBLIC_USER_ID
+}
+
+fn forbidden() -> ErrorResponse {
+    StatusError::new_status(
+        "You don't have access to this resource",
+        StatusCode::FORBIDDEN,
+    )
+}
+
+///
+/// Returns the amount of bytes written to disk and the hash of the content
 ///
 pub async fn write_field_to_file<'a, 'b>(
     mut field: multipart::Field<'a>,
     file_path: &'b std::path::Path,
-) -> AxumResult<usize> {
+) -> AxumResult<(usize, String)> {
     let mut file = fs::File::create(file_path).await.map_err(|e| {
         error!("Failed creating photo file: {e}");
         StatusError::create("Failed creating photo file")
     })?;
 
     let mut file_size = 0;
+    let mut hasher = ContentHasher::new();
 
     while let Some(chunk) = field.try_next().await? {
         file_size += chunk.len();
+        hasher.update(&chunk);
         file.write_all(&chunk).await.map_err(internal_error)?;
     }
 
-    Ok(file_size)
+    Ok((file_size, finalize_hash(hasher)))
+}
+
+///
+/// Moves a fully received file from the staging area into the storage and inserts its photo.
+/// If the owner of the photo already has a photo with the same content, or if it is public,
+/// the staged file is discarded and the existing photo is returned instead.
+/// An existing file is never overwritten, a free name is picked instead: "name (1).jpg"
+///
+pub async fn save_staged_photo(
+    state: &AppState,
+    owner_id: &str,
+    staged_path: &std::path::Path,
+    mut photo_body: PhotoBody,
+) -> AxumResult<Photo> {
+    if let Some(content_hash) = photo_body.content_hash()
+        && let Some(existing) = state
+            .photos_repo
+            .get_photo_by_hash(owner_id, content_hash)
+            .await?
+    {
+        info!(
+            "Discarding upload of {}, it is a duplicate of photo {}",
+            photo_body.name(),
+            existing.id
+        );
+        let _ = fs::remove_file(staged_path).await;
+        return Ok(existing);
+    }
+
+    photo_body.set_name(state.storage.available_name(photo_body.partial_path()));
+
+    let photo_path = state.storage.resolve_photo(photo_body.partial_path());
+    if let Some(parent) = photo_path.parent()
+        && !parent.exists()
+    {
+        fs::create_dir_all(parent).await.map_err(internal_error)?;
+    }
+
+    info!("Saving photo to {}", photo_path.display());
+
+    fs::rename(staged_path, &photo_path)
+        .await
+        .map_err(|e| StatusError::create(format!("Failed moving the photo: {e}")))?;
+
+    // Read once the file has its final name, as the extension tells if it is an image
+    let metadata_path = photo_path.clone();
+    if let Ok(mut metadata) =
+        task::spawn_blocking(move || photo_metadata::read_metadata(metadata_path)).await
+    {
+        state.geocoder.assign_place(&mut metadata);
+        photo_body.set_metadata(metadata);
+    }
+
+    match state.photos_repo.insert_photo(&photo_body).await {
+        Ok(photo) => Ok(photo),
+        Err(e) => {
+            // Insertion failed, move the file back
+            let _ = fs::rename(&photo_path, staged_path).await;
+            Err(e)
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn range_parsing() {
+        assert_eq!(parse_range("bytes=0-99", 1000), Some(Ok((0, 99))));
+        assert_eq!(parse_range("bytes=500-", 1000), Some(Ok((500, 999))));
+        assert_eq!(parse_range("bytes=900-2000", 1000), Some(Ok((900, 999))));
+        assert_eq!(parse_range("bytes=-100", 1000), Some(Ok((900, 999))));
+        assert_eq!(parse_range("bytes=-2000", 1000), Some(Ok((0, 999))));
+
+        assert_eq!(parse_range("bytes=1000-", 1000), Some(Err(())));
+        assert_eq!(parse_range("bytes=-0", 1000), Some(Err(())));
+        assert_eq!(parse_range("bytes=0-", 0), Some(Err(())));
+
+        assert_eq!(parse_range("bytes=0-10, 20-30", 1000), None);
+        assert_eq!(parse_range("bytes=10-5", 1000), None);
+        assert_eq!(parse_range("items=0-10", 1000), None);
+        assert_eq!(parse_range("bytes=a-b", 1000), None);
+    }
+
+    #[test]
+    fn entry_names() {
+        let mut used_names = HashSet::new();
+        used_names.insert(String::from("Trip/a.jpg"));
+        used_names.insert(String::from("Trip/a (1).jpg"));
+        used_names.insert(String::from("README"));
+
+        assert_eq!(
+            available_entry_name(&used_names, String::from("Trip/b.jpg")),
+            "Trip/b.jpg"
+        );
+        assert_eq!(
+            available_entry_name(&used_names, String::from("Trip/a.jpg")),
+            "Trip/a (2).jpg"
+        );
+        assert_eq!(
+            available_entry_name(&used_names, String::from("README")),
+            "README (1)"
+        );
+    }
+
+    #[test]
+    fn not_modified() {
+        let modified = OffsetDateTime::from_unix_timestamp(1474560270).unwrap();
+        let etag = "\"10-20\"";
+
+        let mut headers = HeaderMap::new();
+        assert!(!is_not_modified(&headers, etag, modified));
+
+        headers.insert(
+            header::IF_MODIFIED_SINCE,
+            HeaderValue::from_static("Thu, 22 Sep 2016 16:04:30 GMT"),
+        );
+        assert!(is_not_modified(&headers, etag, modified));
+
+        headers.insert(header::IF_NONE_MATCH, HeaderValue::from_static("\"other\""));
+        assert!(!is_not_modified(&headers, etag, modified));
+
+        headers.insert(
+            header::IF_NONE_MATCH,
+            HeaderValue::from_static("\"other\", W/\"10-20\""),
+        );
+        assert!(is_not_modified(&headers, etag, modified));
+    }
 }
@@ -10,6 +10,7 @@ use tracing_subscriber::layer::SubscriberExt;
 use tracing_subscriber::util::SubscriberInitExt;
 use tracing_subscriber::EnvFilter;
 
+use crate::geocoder::ReverseGeocoder;
 use crate::http::AppState;
 use crate::model::user::{User, PUBLIC_USER_ID};
 use crate::repo::users_repo::UsersRepository;
@@ -19,10 +20,14 @@ use crate::utils::storage_resolver::StorageResolver;
 
 mod cli;
 mod file_scan;
+mod geocoder;
 mod http;
+mod memories;
 mod model;
 mod previews;
 mod repo;
+mod trash;
+mod uploads;
 mod utils;
 
 #[tokio::main]
@@ -56,7 +61,12 @@ async fn main() -> anyhow::Result<()> {
 
     sqlx::migrate!().run(&pool).await?;
 
-    let app_state = AppState::new(pool.clone(), storage_resolver);
+    let geocoder = match &vars.geonames_path {
+        Some(path) => ReverseGeocoder::load(path).context("Failed to load the GeoNames dump")?,
+        None => ReverseGeocoder::default(),
+    };
+
+    let app_state = AppState::new(pool.clone(), storage_resolver, geocoder);
 
     // Migrate the sessions store and delete expired sessions
     let session_store = SqliteStore::new(pool);
@@ -80,6 +90,11 @@ async fn main() -> anyhow::Result<()> {
         file_scan::scan_new_files(app_state.clone());
     }
 
+    if vars.trash_retention_days != 0 {
+        trash::start_purge_task(app_state.clone(), vars.trash_retention_days);
+    }
+    uploads::start_expiry_task(app_state.clone());
+
     info!("Server listening on port {}", vars.server_port);
 
     let http_service = http::router(app_state, session_store).into_make_service();
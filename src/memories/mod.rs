@@ -0,0 +1,193 @@
+use std::cmp::Reverse;
+use std::collections::{BTreeMap, HashSet, VecDeque};
+
+use time::{Date, Duration};
+
+use crate::model::memory::Memory;
+use crate::model::photo::Photo;
+
+/// The `MM-DD` of every day at most `window` days away from the date
+pub fn month_days(date: Date, window: u8) -> Vec<String> {
+    let window = i64::from(window);
+    (-window..=window)
+        .filter_map(|offset| date.checked_add(Duration::days(offset)))
+        .map(|day| format!("{:02}-{:02}", u8::from(day.month()), day.day()))
+        .collect()
+}
+
+/// The same day of the year as the date in the given year, the 28th for February 29 in other years
+fn anniversary(date: Date, year: i32) -> Date {
+    date.replace_year(year)
+        .or_else(|_| {
+            date.replace_day(28)
+                .and_then(|date| date.replace_year(year))
+        })
+        .unwrap_or(date)
+}
+
+/// The year of the anniversary of the date closest to the day, which is the one at most
+/// `window` days away from it for the photos matching [month_days], ex: 2023 for the
+/// 2023-12-30 day around the 2024-01-02 date
+fn anniversary_year(date: Date, day: Date) -> i32 {
+    (day.year() - 1..=day.year() + 1)
+        .min_by_key(|year| (day - anniversary(date, *year)).whole_days().abs())
+        .unwrap_or(day.year())
+}
+
+///
+/// Groups the photos by the year of the anniversary of the date they are closest to, newest
+/// year first, keeping at most `per_year` photos for each.
+/// The picks are spread across the days of the year, taking one photo of each day in turn
+/// starting from the day closest to `date`. Favorites are picked before any other photo
+/// when `favorites_first` is set.
+///
+pub fn pick_memories(
+    date: Date,
+    photos: Vec<Photo>,
+    favorites: &HashSet<i64>,
+    favorites_first: bool,
+    per_year: usize,
+) -> Vec<Memory> {
+    let mut years: BTreeMap<i32, BTreeMap<Date, Vec<Photo>>> = BTreeMap::new();
+    for photo in photos {
+        let day = photo.created_at.date();
+        years
+            .entry(anniversary_year(date, day))
+            .or_default()
+            .entry(day)
+            .or_default()
+            .push(photo);
+    }
+
+    years
+        .into_iter()
+        .rev()
+        .map(|(year, days)| {
+            let target = anniversary(date, year);
+            let mut days = days.into_iter().collect::<Vec<_>>();
+            days.sort_by_key(|(day, _)| ((*day - target).whole_days().abs(), *day));
+
+            // Each day is split into its favorites and the rest, which are only picked
+            // after the favorites of every day
+            let mut rounds = vec![Vec::new(), Vec::new()];
+            for (_, mut photos) in days {
+                photos.sort_by_key(|photo| Reverse(photo.created_at));
+                let (favorite, other): (Vec<_>, Vec<_>) = photos
+                    .into_iter()
+                    .partition(|photo| favorites_first && favorites.contains(&photo.id));
+                rounds[0].push(VecDeque::from(favorite));
+                rounds[1].push(VecDeque::from(other));
+            }
+
+            let mut picked = Vec::with_capacity(per_year);
+            for mut queues in rounds {
+                while picked.len() < per_year && queues.iter().any(|queue| !queue.is_empty()) {
+                    for queue in queues.iter_mut() {
+                        if picked.len() == per_year {
+                            break;
+                        }
+                        if let Some(photo) = queue.pop_front() {
+                            picked.push(photo);
+                        }
+                    }
+                }
+            }
+            picked.sort_by_key(|photo| Reverse(photo.created_at));
+
+            Memory {
+                year,
+                years_ago: date.year() - year,
+                photos: picked,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::{date, datetime};
+    use time::OffsetDateTime;
+
+    use super::*;
+
+    fn photo(id: i64, created_at: OffsetDateTime) -> Photo {
+        Photo {
+            id,
+            user_id: "user".to_string(),
+            name: format!("{id}.jpg"),
+            created_at,
+            file_size: 0,
+            folder: None,
+            content_hash: None,
+            trashed_at: None,
+        }
+    }
+
+    fn ids(memory: &Memory) -> Vec<i64> {
+        memory.photos.iter().map(|photo| photo.id).collect()
+    }
+
+    #[test]
+    fn month_days_window() {
+        assert_eq!(month_days(date!(2024 - 03 - 01), 0), vec!["03-01"]);
+        assert_eq!(
+            month_days(date!(2024 - 03 - 01), 1),
+            vec!["02-29", "03-01", "03-02"]
+        );
+        assert_eq!(
+            month_days(date!(2023 - 12 - 31), 1),
+            vec!["12-30", "12-31", "01-01"]
+        );
+    }
+
+    #[test]
+    fn picks_spread_across_days() {
+        let photos = vec![
+            photo(1, datetime!(2020-06-10 10:00 UTC)),
+            photo(2, datetime!(2020-06-10 11:00 UTC)),
+            photo(3, datetime!(2020-06-10 12:00 UTC)),
+            photo(4, datetime!(2020-06-11 10:00 UTC)),
+            photo(5, datetime!(2020-06-12 10:00 UTC)),
+            photo(6, datetime!(2018-06-10 10:00 UTC)),
+        ];
+        let favorites = HashSet::from([2]);
+
+        let memories = pick_memories(date!(2024 - 06 - 10), photos.clone(), &favorites, false, 3);
+        assert_eq!(memories.len(), 2);
+        assert_eq!((memories[0].year, memories[0].years_ago), (2020, 4));
+        // The newest photo of each day, starting from the closest day
+        assert_eq!(ids(&memories[0]), vec![5, 4, 3]);
+        assert_eq!((memories[1].year, ids(&memories[1])), (2018, vec![6]));
+
+        let memories = pick_memories(date!(2024 - 06 - 10), photos, &favorites, true, 2);
+        assert_eq!(ids(&memories[0]), vec![3, 2]);
+    }
+
+    #[test]
+    fn memories_across_new_year() {
+        let photos = vec![
+            photo(1, datetime!(2023-01-04 10:00 UTC)),
+            photo(2, datetime!(2022-12-30 10:00 UTC)),
+            photo(3, datetime!(2021-12-31 10:00 UTC)),
+        ];
+
+        let memories = pick_memories(date!(2024 - 01 - 02), photos, &HashSet::new(), false, 5);
+        let years = memories
+            .iter()
+            .map(|memory| (memory.year, memory.years_ago, ids(memory)))
+            .collect::<Vec<_>>();
+        assert_eq!(years, vec![(2023, 1, vec![1, 2]), (2022, 2, vec![3])]);
+    }
+
+    #[test]
+    fn leap_day_anniversary() {
+        assert_eq!(
+            anniversary(date!(2024 - 02 - 29), 2023),
+            date!(2023 - 02 - 28)
+        );
+        assert_eq!(
+            anniversary_year(date!(2024 - 02 - 29), date!(2021 - 03 - 01)),
+            2021
+        );
+    }
+}
@@ -0,0 +1,18 @@
+use serde::Serialize;
+use time::serde::timestamp;
+use time::OffsetDateTime;
+
+/// A user defined collection of photos, independent of the folders they are stored in
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Album {
+    pub id: i64,
+    /// The user who created the album
+    pub user_id: String,
+    pub name: String,
+    pub description: Option<String>,
+    /// Unset when the album has no cover, in which case the first photo can be used instead
+    pub cover_photo_id: Option<i64>,
+    #[serde(with = "timestamp")]
+    pub created_at: OffsetDateTime,
+}
@@ -0,0 +1,31 @@
+use serde::Serialize;
+use time::serde::timestamp;
+use time::OffsetDateTime;
+
+/// A folder of a user, the photos it contains are the ones visible to the current user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Folder {
+    pub user_id: String,
+    pub name: String,
+    pub count: i64,
+    /// The newest photo, `None` when the folder is empty
+    pub cover_photo_id: Option<i64>,
+    #[serde(with = "timestamp::option")]
+    pub first_created_at: Option<OffsetDateTime>,
+    #[serde(with = "timestamp::option")]
+    pub last_created_at: Option<OffsetDateTime>,
+}
+
+impl Folder {
+    pub fn empty(user_id: String, name: String) -> Self {
+        Self {
+            user_id,
+            name,
+            count: 0,
+            cover_photo_id: None,
+            first_created_at: None,
+            last_created_at: None,
+        }
+    }
+}
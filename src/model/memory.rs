@@ -0,0 +1,12 @@
+use serde::Serialize;
+
+use crate::model::photo::Photo;
+
+/// Photos taken around the same day of the year in a previous year
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct Memory {
+    pub year: i32,
+    pub years_ago: i32,
+    pub photos: Vec<Photo>,
+}
@@ -1,2 +1,17 @@
+pub mod album;
+pub mod folder;
+pub mod memory;
 pub mod photo;
+pub mod photo_changes;
+pub mod photo_comment;
+pub mod photo_details;
+pub mod photo_location;
+pub mod photo_metadata;
+pub mod photo_rating;
+pub mod photo_share;
+pub mod place;
+pub mod share_link;
+pub mod tag;
+pub mod timeline;
+pub mod upload;
 pub mod user;
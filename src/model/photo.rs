@@ -1,8 +1,12 @@
 use serde::Serialize;
+use std::fmt;
+use std::str::FromStr;
 use time::OffsetDateTime;
 
 use time::serde::timestamp;
 
+use crate::model::photo_metadata::PhotoMetadata;
+
 pub trait PhotoBase {
     fn user_id(&self) -> &String;
 
@@ -14,6 +18,9 @@ pub trait PhotoBase {
 
     fn folder_name(&self) -> Option<&String>;
 
+    /// Hex encoded SHA-256 of the file, `None` if it was not computed yet
+    fn content_hash(&self) -> Option<&String>;
+
     fn full_name(&self) -> String {
         let folder_path = match self.folder_name().as_ref() {
             None => String::new(),
@@ -28,7 +35,7 @@ pub trait PhotoBase {
     }
 }
 
-#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[derive(Debug, Clone, PartialEq, Eq, Serialize, sqlx::FromRow)]
 #[serde(rename_all = "camelCase")]
 pub struct Photo {
     pub id: i64,
@@ -38,6 +45,9 @@ pub struct Photo {
     pub created_at: OffsetDateTime,
     pub file_size: i64,
     pub folder: Option<String>,
+    pub content_hash: Option<String>,
+    #[serde(with = "timestamp::option")]
+    pub trashed_at: Option<OffsetDateTime>,
 }
 
 impl PhotoBase for Photo {
@@ -60,6 +70,10 @@ impl PhotoBase for Photo {
     fn folder_name(&self) -> Option<&String> {
         self.folder.as_ref()
     }
+
+    fn content_hash(&self) -> Option<&String> {
+        self.content_hash.as_ref()
+    }
 }
 
 impl Photo {
@@ -70,6 +84,29 @@ impl Photo {
     pub fn partial_preview_path(&self) -> String {
         format!("{}.jpg", self.id)
     }
+
+    /// Folder inside the trash holding only this photo, the id keeps apart photos
+    /// trashed from the same path
+    pub fn partial_trash_folder(&self) -> String {
+        format!("{}/{}", self.user_id, self.id)
+    }
+
+    pub fn partial_trash_path(&self) -> String {
+        format!("{}/{}", self.partial_trash_folder(), self.full_name())
+    }
+
+    pub fn is_trashed(&self) -> bool {
+        self.trashed_at.is_some()
+    }
+}
+
+/// A photo as returned by the photo lists, along with the number of comments on it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoListItem {
+    #[serde(flatten)]
+    pub photo: Photo,
+    pub comment_count: i64,
 }
 
 #[derive(Debug, Clone)]
@@ -79,6 +116,11 @@ pub struct PhotoBody {
     created_at: OffsetDateTime,
     file_size: i64,
     folder: Option<String>,
+    content_hash: Option<String>,
+    metadata: Option<PhotoMetadata>,
+    caption: Option<String>,
+    /// Star rating the file was given by its owner in another application
+    rating: Option<i64>,
 }
 
 impl PhotoBase for PhotoBody {
@@ -101,6 +143,10 @@ impl PhotoBase for PhotoBody {
     fn folder_name(&self) -> Option<&String> {
         self.folder.as_ref()
     }
+
+    fn content_hash(&self) -> Option<&String> {
+        self.content_hash.as_ref()
+    }
 }
 
 impl PhotoBody {
@@ -117,10 +163,108 @@ impl PhotoBody {
             created_at,
             file_size,
             folder,
+            content_hash: None,
+            metadata: None,
+            caption: None,
+            rating: None,
         }
     }
 
+    pub fn metadata(&self) -> Option<&PhotoMetadata> {
+        self.metadata.as_ref()
+    }
+
+    pub fn metadata_mut(&mut self) -> Option<&mut PhotoMetadata> {
+        self.metadata.as_mut()
+    }
+
+    pub fn caption(&self) -> Option<&String> {
+        self.caption.as_ref()
+    }
+
+    pub fn rating(&self) -> Option<i64> {
+        self.rating
+    }
+
+    pub fn set_name(&mut self, value: String) {
+        self.name = value;
+    }
+
     pub fn set_file_size(&mut self, value: i64) {
         self.file_size = value;
     }
+
+    pub fn set_content_hash(&mut self, value: String) {
+        self.content_hash = Some(value);
+    }
+
+    pub fn set_metadata(&mut self, value: PhotoMetadata) {
+        self.metadata = Some(value);
+    }
+
+    pub fn set_caption(&mut self, value: String) {
+        self.caption = Some(value);
+    }
+
+    pub fn set_rating(&mut self, value: i64) {
+        self.rating = Some(value);
+    }
+}
+
+/// Position of the last photo of a page, in the `(created_at desc, id desc)` order
+/// in which photos are listed.
+///
+/// It is sent to clients as an opaque `<unix nanoseconds>_<id>` string.
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub struct PhotoCursor {
+    pub created_at: OffsetDateTime,
+    pub id: i64,
+}
+
+impl From<&Photo> for PhotoCursor {
+    fn from(photo: &Photo) -> Self {
+        Self {
+            created_at: photo.created_at,
+            id: photo.id,
+        }
+    }
+}
+
+impl fmt::Display for PhotoCursor {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}_{}", self.created_at.unix_timestamp_nanos(), self.id)
+    }
+}
+
+impl FromStr for PhotoCursor {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let (timestamp, id) = s.split_once('_').ok_or(())?;
+        let timestamp = timestamp.parse().map_err(|_| ())?;
+
+        Ok(Self {
+            created_at: OffsetDateTime::from_unix_timestamp_nanos(timestamp).map_err(|_| ())?,
+            id: id.parse().map_err(|_| ())?,
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    #[test]
+    fn cursor_round_trip() {
+        let cursor = PhotoCursor {
+            created_at: datetime!(2016-09-22 16:04:30.25 UTC),
+            id: 42,
+        };
+
+        assert_eq!(cursor.to_string().parse::<PhotoCursor>(), Ok(cursor));
+        assert_eq!("1474560270".parse::<PhotoCursor>(), Err(()));
+        assert_eq!("abc_42".parse::<PhotoCursor>(), Err(()));
+    }
 }
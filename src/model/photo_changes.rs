@@ -0,0 +1,17 @@
+use serde::Serialize;
+
+use crate::model::photo::Photo;
+
+/// Everything that changed in the photos visible to a user since a sync token
+#[derive(Debug, Clone, Default, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoChanges {
+    /// Photos that were added or modified
+    pub upserted: Vec<Photo>,
+    /// Ids of the photos that were deleted or are no longer visible to the user
+    pub deleted: Vec<i64>,
+    pub favorited: Vec<i64>,
+    pub unfavorited: Vec<i64>,
+    /// Token to pass on the next sync
+    pub token: i64,
+}
@@ -0,0 +1,21 @@
+use serde::Serialize;
+use time::serde::timestamp;
+use time::OffsetDateTime;
+
+/// Longest comment allowed, in characters
+pub const MAX_COMMENT_LENGTH: usize = 4000;
+
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoComment {
+    pub id: i64,
+    pub photo_id: i64,
+    /// The author of the comment
+    pub user_id: String,
+    pub text: String,
+    #[serde(with = "timestamp")]
+    pub created_at: OffsetDateTime,
+    /// Set when the comment was edited after being posted
+    #[serde(with = "timestamp::option")]
+    pub edited_at: Option<OffsetDateTime>,
+}
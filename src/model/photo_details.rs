@@ -0,0 +1,17 @@
+use serde::Serialize;
+
+use crate::model::photo::Photo;
+
+/// Longest caption allowed, in characters
+pub const MAX_CAPTION_LENGTH: usize = 2000;
+
+/// A photo along with the annotations that are not part of the photos list
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoDetails {
+    #[serde(flatten)]
+    pub photo: Photo,
+    pub caption: Option<String>,
+    /// Sorted by name
+    pub tags: Vec<String>,
+}
@@ -0,0 +1,90 @@
+use serde::Serialize;
+use std::str::FromStr;
+
+/// Area of the map in degrees, `west` is greater than `east` when it crosses the antimeridian
+#[derive(Debug, Clone, Copy, PartialEq)]
+pub struct BoundingBox {
+    pub west: f64,
+    pub south: f64,
+    pub east: f64,
+    pub north: f64,
+}
+
+/// Parses the `<west>,<south>,<east>,<north>` format
+impl FromStr for BoundingBox {
+    type Err = ();
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        let values = s
+            .split(',')
+            .map(|value| value.trim().parse::<f64>().map_err(|_| ()))
+            .collect::<Result<Vec<_>, _>>()?;
+
+        let [west, south, east, north] = values[..] else {
+            return Err(());
+        };
+
+        let longitudes = -180.0..=180.0;
+        let latitudes = -90.0..=90.0;
+        if !longitudes.contains(&west)
+            || !longitudes.contains(&east)
+            || !latitudes.contains(&south)
+            || !latitudes.contains(&north)
+            || south > north
+        {
+            return Err(());
+        }
+
+        Ok(Self {
+            west,
+            south,
+            east,
+            north,
+        })
+    }
+}
+
+/// Geotagged photos close to each other on the map
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoCluster {
+    pub count: i64,
+    /// The average position of the photos
+    pub latitude: f64,
+    pub longitude: f64,
+    /// The newest photo of the cluster
+    pub photo_id: i64,
+}
+
+#[derive(Debug, Clone, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoLocation {
+    pub photo_id: i64,
+    pub latitude: f64,
+    pub longitude: f64,
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bounding_box_parsing() {
+        assert_eq!(
+            "-10.5, 40,20,55.25".parse(),
+            Ok(BoundingBox {
+                west: -10.5,
+                south: 40.0,
+                east: 20.0,
+                north: 55.25,
+            })
+        );
+        assert!("170,-10,-170,10".parse::<BoundingBox>().is_ok());
+
+        assert_eq!("1,2,3".parse::<BoundingBox>(), Err(()));
+        assert_eq!("1,2,3,4,5".parse::<BoundingBox>(), Err(()));
+        assert_eq!("0,10,20,5".parse::<BoundingBox>(), Err(()));
+        assert_eq!("0,0,200,10".parse::<BoundingBox>(), Err(()));
+        assert_eq!("a,0,20,10".parse::<BoundingBox>(), Err(()));
+    }
+}
@@ -0,0 +1,44 @@
+use serde::Serialize;
+
+use crate::model::place::Place;
+
+/// The EXIF metadata of a photo, every field is `None` when its tag is missing
+#[derive(Debug, Clone, Default, PartialEq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoMetadata {
+    pub camera_make: Option<String>,
+    pub camera_model: Option<String>,
+    pub lens_model: Option<String>,
+    /// In seconds
+    pub exposure_time: Option<f64>,
+    pub f_number: Option<f64>,
+    pub iso: Option<i64>,
+    /// In millimeters
+    pub focal_length: Option<f64>,
+    pub width: Option<i64>,
+    pub height: Option<i64>,
+    pub orientation: Option<i64>,
+    pub latitude: Option<f64>,
+    pub longitude: Option<f64>,
+    /// In meters above the sea level
+    pub altitude: Option<f64>,
+    /// Found from the GPS coordinates
+    pub city: Option<String>,
+    pub region: Option<String>,
+    pub country: Option<String>,
+    /// Version of the GeoNames dump the coordinates were looked up in, if they were
+    #[serde(skip)]
+    pub geocoded_with: Option<String>,
+}
+
+impl PhotoMetadata {
+    pub fn is_empty(&self) -> bool {
+        self == &Self::default()
+    }
+
+    pub fn set_place(&mut self, place: Place) {
+        self.city = Some(place.city);
+        self.region = place.region;
+        self.country = Some(place.country);
+    }
+}
@@ -0,0 +1,25 @@
+use serde::{Deserialize, Serialize};
+
+/// Highest star rating a photo can have, 0 means unrated
+pub const MAX_RATING: i64 = 5;
+
+/// Color labels used to group photos while culling them, the same as in Lightroom
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Serialize, Deserialize, sqlx::Type)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum ColorLabel {
+    Red,
+    Yellow,
+    Green,
+    Blue,
+    Purple,
+}
+
+/// The rating and color label a user gave to a photo
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoRating {
+    pub photo_id: i64,
+    pub rating: i64,
+    pub color_label: Option<ColorLabel>,
+}
@@ -0,0 +1,34 @@
+use serde::{Deserialize, Serialize};
+use time::serde::timestamp;
+use time::OffsetDateTime;
+
+/// What a user can do with the photos shared with them, ordered from the least to the most
+#[derive(
+    Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord, Serialize, Deserialize, sqlx::Type,
+)]
+#[serde(rename_all = "lowercase")]
+#[sqlx(rename_all = "lowercase")]
+pub enum SharePermission {
+    /// View, download and favorite the photos or add them to albums
+    Read,
+    /// Also upload photos to a shared folder and move the shared photos to the trash
+    Contribute,
+}
+
+/// Grants a user access to a single photo or to a whole folder of another user
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PhotoShare {
+    pub id: i64,
+    /// The user who shared the photos
+    pub owner_id: String,
+    /// The user the photos are shared with
+    pub user_id: String,
+    /// Set when a single photo is shared
+    pub photo_id: Option<i64>,
+    /// Set when a folder of the owner is shared, including its subfolders and the photos added later
+    pub folder: Option<String>,
+    pub permission: SharePermission,
+    #[serde(with = "timestamp")]
+    pub created_at: OffsetDateTime,
+}
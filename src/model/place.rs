@@ -0,0 +1,41 @@
+use serde::{Deserialize, Serialize};
+
+/// Human readable location of a photo, found from its GPS coordinates
+#[derive(Debug, Clone, PartialEq, Eq)]
+pub struct Place {
+    pub city: String,
+    pub region: Option<String>,
+    pub country: String,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum PlaceLevel {
+    Country,
+    Region,
+    #[default]
+    City,
+}
+
+impl PlaceLevel {
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            PlaceLevel::Country => "country",
+            PlaceLevel::Region => "region",
+            PlaceLevel::City => "city",
+        }
+    }
+}
+
+/// The photos taken in the same place, `region` and `city` are `None` when grouping
+/// by a broader level
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct PlaceGroup {
+    pub country: String,
+    pub region: Option<String>,
+    pub city: Option<String>,
+    pub count: i64,
+    /// The newest photo taken in the place
+    pub photo_id: i64,
+}
@@ -0,0 +1,37 @@
+use serde::{Serialize, Serializer};
+use time::serde::timestamp;
+use time::OffsetDateTime;
+
+/// A link giving anyone with its token access to a folder or to a selection of photos
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ShareLink {
+    pub id: i64,
+    pub token: String,
+    /// The user who created the link
+    pub user_id: String,
+    /// The shared folder of the user, `None` when a selection of photos is shared
+    pub folder: Option<String>,
+    #[serde(with = "timestamp::option")]
+    pub expires_at: Option<OffsetDateTime>,
+    #[serde(rename = "hasPassword", serialize_with = "serialize_is_some")]
+    pub password_hash: Option<String>,
+    pub allow_download: bool,
+    #[serde(with = "timestamp")]
+    pub created_at: OffsetDateTime,
+}
+
+impl ShareLink {
+    pub fn is_expired(&self) -> bool {
+        self.expires_at
+            .is_some_and(|expires_at| expires_at <= OffsetDateTime::now_utc())
+    }
+}
+
+/// Only whether the password is set is exposed, never its hash
+fn serialize_is_some<S: Serializer>(
+    value: &Option<String>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.serialize_bool(value.is_some())
+}
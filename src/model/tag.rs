@@ -0,0 +1,52 @@
+use serde::Serialize;
+
+/// Longest tag name allowed, in characters
+pub const MAX_TAG_LENGTH: usize = 64;
+
+/// A tag along with the number of photos visible to the user that have it
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TagCount {
+    pub name: String,
+    pub count: i64,
+}
+
+///
+/// Trims the tag and collapses its inner whitespace, so that the same tag typed slightly
+/// differently is not stored twice. The case is kept, as tags are compared case insensitively.
+///
+pub fn normalize_tag(tag: &str) -> Result<String, String> {
+    let tag = tag.split_whitespace().collect::<Vec<_>>().join(" ");
+
+    if tag.is_empty() {
+        return Err("Tags can't be empty".to_string());
+    }
+    if tag.chars().count() > MAX_TAG_LENGTH {
+        return Err(format!(
+            "Tags can't be longer than {MAX_TAG_LENGTH} characters"
+        ));
+    }
+    if tag.chars().any(char::is_control) {
+        return Err("Tags can't contain control characters".to_string());
+    }
+
+    Ok(tag)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn tag_normalization() {
+        assert_eq!(normalize_tag("  Beach "), Ok("Beach".to_string()));
+        assert_eq!(
+            normalize_tag("Summer \t 2016"),
+            Ok("Summer 2016".to_string())
+        );
+
+        assert!(normalize_tag("   ").is_err());
+        assert!(normalize_tag(&"a".repeat(MAX_TAG_LENGTH + 1)).is_err());
+        assert!(normalize_tag("a\u{7f}b").is_err());
+    }
+}
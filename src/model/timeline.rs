@@ -0,0 +1,113 @@
+use serde::{Deserialize, Serialize};
+use time::{Date, Month, OffsetDateTime};
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum TimelineGranularity {
+    Year,
+    #[default]
+    Month,
+    Day,
+}
+
+impl TimelineGranularity {
+    /// Also the name of the SQLite date modifier unit
+    pub fn as_str(&self) -> &'static str {
+        match self {
+            TimelineGranularity::Year => "year",
+            TimelineGranularity::Month => "month",
+            TimelineGranularity::Day => "day",
+        }
+    }
+}
+
+/// The photos created in the same year, month or day
+#[derive(Debug, Clone, PartialEq, Eq, Serialize)]
+#[serde(rename_all = "camelCase")]
+pub struct TimelineBucket {
+    /// `YYYY`, `YYYY-MM` or `YYYY-MM-DD` depending on the granularity
+    pub key: String,
+    pub count: i64,
+    /// Unix timestamp of the start of the bucket
+    pub start: i64,
+    /// Unix timestamp of the start of the next bucket
+    pub end: i64,
+}
+
+/// Returns the `[start, end)` interval of the bucket with the given key
+pub fn bucket_bounds(key: &str) -> Option<(OffsetDateTime, OffsetDateTime)> {
+    let parts = key
+        .split('-')
+        .map(|part| part.parse::<u16>().ok())
+        .collect::<Option<Vec<_>>>()?;
+
+    let (start, end) = match (parts.as_slice(), key.len()) {
+        ([year], 4) => {
+            let year = i32::from(*year);
+            (
+                Date::from_calendar_date(year, Month::January, 1).ok()?,
+                Date::from_calendar_date(year + 1, Month::January, 1).ok()?,
+            )
+        }
+        ([year, month], 7) => {
+            let month = Month::try_from(u8::try_from(*month).ok()?).ok()?;
+            let start = Date::from_calendar_date(i32::from(*year), month, 1).ok()?;
+            let end_year = if month == Month::December {
+                start.year() + 1
+            } else {
+                start.year()
+            };
+            (
+                start,
+                Date::from_calendar_date(end_year, month.next(), 1).ok()?,
+            )
+        }
+        ([year, month, day], 10) => {
+            let month = Month::try_from(u8::try_from(*month).ok()?).ok()?;
+            let start =
+                Date::from_calendar_date(i32::from(*year), month, u8::try_from(*day).ok()?).ok()?;
+            (start, start.next_day()?)
+        }
+        _ => return None,
+    };
+
+    Some((start.midnight().assume_utc(), end.midnight().assume_utc()))
+}
+
+#[cfg(test)]
+mod tests {
+    use time::macros::datetime;
+
+    use super::*;
+
+    #[test]
+    fn bucket_key_bounds() {
+        assert_eq!(
+            bucket_bounds("2016"),
+            Some((
+                datetime!(2016-01-01 00:00:00 UTC),
+                datetime!(2017-01-01 00:00:00 UTC)
+            ))
+        );
+        assert_eq!(
+            bucket_bounds("2016-12"),
+            Some((
+                datetime!(2016-12-01 00:00:00 UTC),
+                datetime!(2017-01-01 00:00:00 UTC)
+            ))
+        );
+        assert_eq!(
+            bucket_bounds("2016-02-29"),
+            Some((
+                datetime!(2016-02-29 00:00:00 UTC),
+                datetime!(2016-03-01 00:00:00 UTC)
+            ))
+        );
+
+        assert_eq!(bucket_bounds("2016-13"), None);
+        assert_eq!(bucket_bounds("2015-02-29"), None);
+        assert_eq!(bucket_bounds("2016-1"), None);
+        assert_eq!(bucket_bounds("20161"), None);
+        assert_eq!(bucket_bounds(""), None);
+    }
+}
@@ -0,0 +1,41 @@
+use time::OffsetDateTime;
+
+use crate::model::photo::PhotoBody;
+use crate::model::user::PUBLIC_USER_ID;
+
+/// A resumable upload which becomes a photo once all of its bytes have been received
+#[derive(Debug, Clone)]
+pub struct Upload {
+    pub id: String,
+    /// The user who started the upload
+    pub user_id: String,
+    pub name: String,
+    pub folder: Option<String>,
+    pub time_created: OffsetDateTime,
+    pub make_public: bool,
+    pub upload_length: i64,
+    pub upload_offset: i64,
+    pub started_at: OffsetDateTime,
+    /// When bytes were last appended, or when it was started if none were
+    pub updated_at: OffsetDateTime,
+}
+
+impl Upload {
+    pub fn is_complete(&self) -> bool {
+        self.upload_offset == self.upload_length
+    }
+
+    pub fn photo_body(&self) -> PhotoBody {
+        PhotoBody::new(
+            if self.make_public {
+                String::from(PUBLIC_USER_ID)
+            } else {
+                self.user_id.clone()
+            },
+            self.name.clone(),
+            self.time_created,
+            self.upload_length,
+            self.folder.clone(),
+        )
+    }
+}
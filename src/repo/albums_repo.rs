@@ -0,0 +1,213 @@
+use crate::model::album::Album;
+use crate::model::photo::Photo;
+use crate::model::user::PUBLIC_USER_ID;
+use crate::utils::internal_error;
+use axum::response::ErrorResponse;
+use sqlx::{query, query_as, query_scalar, SqlitePool};
+use time::OffsetDateTime;
+
+#[derive(Clone)]
+pub struct AlbumsRepository {
+    pool: SqlitePool,
+}
+
+impl AlbumsRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get_album(&self, id: i64) -> Result<Option<Album>, ErrorResponse> {
+        query_as!(Album, "select * from albums where id = $1", id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal_error)
+    }
+
+    /// Returns the albums created by the user, newest first
+    pub async fn get_albums(&self, user_id: impl AsRef<str>) -> Result<Vec<Album>, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        query_as!(
+            Album,
+            "select * from albums where user_id = $1 order by created_at desc, id desc",
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Returns the photos in the album that are still visible to the user, in the album's order
+    pub async fn get_album_photos(
+        &self,
+        album_id: i64,
+        user_id: impl AsRef<str>,
+    ) -> Result<Vec<Photo>, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        query_as!(
+            Photo,
+            "select photos.* from album_photos
+            join photos on photos.id = album_photos.photo_id
+            where album_photos.album_id = $1 and photos.trashed_at is null and (
+                photos.user_id in ($2, $3) or photos.id in (select photo_id from shared_photos where user_id = $2)
+            )
+            order by album_photos.position, album_photos.photo_id",
+            album_id,
+            user_id,
+            PUBLIC_USER_ID
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    pub async fn contains_photo(
+        &self,
+        album_id: i64,
+        photo_id: i64,
+    ) -> Result<bool, ErrorResponse> {
+        query_scalar!(
+            r#"select exists(select 1 from album_photos where album_id = $1 and photo_id = $2) as "exists!: bool""#,
+            album_id,
+            photo_id
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    pub async fn insert_album(
+        &self,
+        user_id: impl AsRef<str>,
+        name: impl AsRef<str>,
+        description: Option<&str>,
+    ) -> Result<Album, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        let name = name.as_ref();
+        let created_at = OffsetDateTime::now_utc();
+        query_as!(
+            Album,
+            "insert into albums (user_id, name, description, created_at) values ($1, $2, $3, $4) returning *",
+            user_id,
+            name,
+            description,
+            created_at
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    pub async fn update_album(&self, album: &Album) -> Result<(), ErrorResponse> {
+        query!(
+            "update albums set name = $2, description = $3, cover_photo_id = $4 where id = $1",
+            album.id,
+            album.name,
+            album.description,
+            album.cover_photo_id
+        )
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+        .map_err(internal_error)
+    }
+
+    /// Deletes the album, the photos in it are left untouched
+    pub async fn delete_album(&self, id: i64) -> Result<(), ErrorResponse> {
+        query!("delete from albums where id = $1", id)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(internal_error)
+    }
+
+    /// Appends the photos to the end of the album, photos already in it keep their position
+    pub async fn add_photos(&self, album_id: i64, photo_ids: &[i64]) -> Result<(), ErrorResponse> {
+        let mut tx = self.pool.begin().await.map_err(internal_error)?;
+
+        let mut position = query_scalar!(
+            r#"select coalesce(max(position) + 1, 0) as "position!: i64" from album_photos where album_id = $1"#,
+            album_id
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+
+        for photo_id in photo_ids {
+            let inserted = query!(
+                "insert or ignore into album_photos (album_id, photo_id, position) values ($1, $2, $3)",
+                album_id,
+                photo_id,
+                position
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+
+            position += inserted.rows_affected() as i64;
+        }
+
+        tx.commit().await.map_err(internal_error)
+    }
+
+    /// Removes the photo from the album, also unsetting it as the cover
+    pub async fn remove_photo(&self, album_id: i64, photo_id: i64) -> Result<(), ErrorResponse> {
+        let mut tx = self.pool.begin().await.map_err(internal_error)?;
+
+        query!(
+            "delete from album_photos where album_id = $1 and photo_id = $2",
+            album_id,
+            photo_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+
+        query!(
+            "update albums set cover_photo_id = null where id = $1 and cover_photo_id = $2",
+            album_id,
+            photo_id
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+
+        tx.commit().await.map_err(internal_error)
+    }
+
+    ///
+    /// Moves the given photos to the start of the album in the given order,
+    /// the photos that were not given keep their relative order after them
+    ///
+    pub async fn reorder_photos(
+        &self,
+        album_id: i64,
+        photo_ids: &[i64],
+    ) -> Result<(), ErrorResponse> {
+        let mut tx = self.pool.begin().await.map_err(internal_error)?;
+
+        let photos_count = photo_ids.len() as i64;
+        query!(
+            "update album_photos set position = position + $2 where album_id = $1",
+            album_id,
+            photos_count
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+
+        for (position, photo_id) in photo_ids.iter().enumerate() {
+            let position = position as i64;
+            query!(
+                "update album_photos set position = $3 where album_id = $1 and photo_id = $2",
+                album_id,
+                photo_id,
+                position
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+        }
+
+        tx.commit().await.map_err(internal_error)
+    }
+}
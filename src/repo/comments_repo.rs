@@ -0,0 +1,121 @@
+use std::collections::HashMap;
+
+use crate::model::photo_comment::PhotoComment;
+use crate::utils::internal_error;
+use axum::response::ErrorResponse;
+use sqlx::{query, query_as, QueryBuilder, Sqlite, SqlitePool};
+use time::OffsetDateTime;
+
+#[derive(Clone)]
+pub struct CommentsRepository {
+    pool: SqlitePool,
+}
+
+impl CommentsRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get_comment(&self, id: i64) -> Result<Option<PhotoComment>, ErrorResponse> {
+        query_as!(
+            PhotoComment,
+            "select * from photo_comments where id = $1",
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Returns the comments on the photo, oldest first
+    pub async fn get_comments(&self, photo_id: i64) -> Result<Vec<PhotoComment>, ErrorResponse> {
+        query_as!(
+            PhotoComment,
+            "select * from photo_comments where photo_id = $1 order by id",
+            photo_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Returns the number of comments on each of the photos, photos without comments are left out
+    pub async fn get_comment_counts(
+        &self,
+        photo_ids: &[i64],
+    ) -> Result<HashMap<i64, i64>, ErrorResponse> {
+        let mut counts = HashMap::new();
+
+        for chunk in photo_ids.chunks(512) {
+            let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+                "select photo_id, count(*) from photo_comments where photo_id in (",
+            );
+
+            let mut separated = query_builder.separated(", ");
+            for photo_id in chunk {
+                separated.push_bind(photo_id);
+            }
+            separated.push_unseparated(") group by photo_id");
+
+            counts.extend(
+                query_builder
+                    .build_query_as::<(i64, i64)>()
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(internal_error)?,
+            );
+        }
+
+        Ok(counts)
+    }
+
+    pub async fn insert_comment(
+        &self,
+        photo_id: i64,
+        user_id: impl AsRef<str>,
+        text: impl AsRef<str>,
+    ) -> Result<PhotoComment, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        let text = text.as_ref();
+        let created_at = OffsetDateTime::now_utc();
+        query_as!(
+            PhotoComment,
+            "insert into photo_comments (photo_id, user_id, text, created_at) values ($1, $2, $3, $4) returning *",
+            photo_id,
+            user_id,
+            text,
+            created_at
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Replaces the text of the comment, marking it as edited
+    pub async fn update_comment(
+        &self,
+        id: i64,
+        text: impl AsRef<str>,
+    ) -> Result<PhotoComment, ErrorResponse> {
+        let text = text.as_ref();
+        let edited_at = OffsetDateTime::now_utc();
+        query_as!(
+            PhotoComment,
+            "update photo_comments set text = $2, edited_at = $3 where id = $1 returning *",
+            id,
+            text,
+            edited_at
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    pub async fn delete_comment(&self, id: i64) -> Result<(), ErrorResponse> {
+        query!("delete from photo_comments where id = $1", id)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(internal_error)
+    }
+}
@@ -0,0 +1,232 @@
+use crate::model::folder::Folder;
+use crate::model::photo::Photo;
+use crate::model::user::PUBLIC_USER_ID;
+use crate::utils::internal_error;
+use axum::response::ErrorResponse;
+use sqlx::{query, query_as, query_scalar, SqliteConnection, SqlitePool};
+
+#[derive(Clone)]
+pub struct FoldersRepository {
+    pool: SqlitePool,
+}
+
+impl FoldersRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    ///
+    /// Returns the folders containing photos visible to the user, optionally only the ones
+    /// of the given owner or the one with the given name.
+    /// Trashed photos are not counted.
+    ///
+    pub async fn get_folders(
+        &self,
+        user_id: impl AsRef<str>,
+        owner_id: Option<&str>,
+        folder: Option<&str>,
+    ) -> Result<Vec<Folder>, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        query_as!(
+            Folder,
+            r#"select user_id as "user_id!: String", folder as "name!: String", count as "count!: i64",
+            (select id from photos p where p.user_id = f.user_id and p.folder = f.folder and p.trashed_at is null
+                and (p.user_id in ($1, $4) or p.id in (select photo_id from shared_photos where user_id = $1))
+                order by p.created_at desc, p.id desc limit 1) as "cover_photo_id?: i64",
+            first_created_at as "first_created_at?: _", last_created_at as "last_created_at?: _" from (
+                select user_id, folder, count(*) as count,
+                min(created_at) as first_created_at, max(created_at) as last_created_at
+                from photos
+                where trashed_at is null and folder is not null
+                and (user_id in ($1, $4) or id in (select photo_id from shared_photos where user_id = $1))
+                and ($2 is null or user_id = $2) and ($3 is null or folder = $3)
+                group by user_id, folder
+            ) f order by user_id, folder"#,
+            user_id,
+            owner_id,
+            folder,
+            PUBLIC_USER_ID
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Returns all the photos of the owner in the folder, the trashed ones included
+    pub async fn get_folder_photos(
+        &self,
+        owner_id: impl AsRef<str>,
+        folder: impl AsRef<str>,
+    ) -> Result<Vec<Photo>, ErrorResponse> {
+        let owner_id = owner_id.as_ref();
+        let folder = folder.as_ref();
+        query_as!(
+            Photo,
+            "select * from photos where user_id = $1 and folder = $2",
+            owner_id,
+            folder
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Returns all the photos of the owner in the folder and in its subfolders, the trashed ones included
+    pub async fn get_folder_tree_photos(
+        &self,
+        owner_id: impl AsRef<str>,
+        folder: impl AsRef<str>,
+    ) -> Result<Vec<Photo>, ErrorResponse> {
+        let owner_id = owner_id.as_ref();
+        let folder = folder.as_ref();
+        query_as!(
+            Photo,
+            "select * from photos where user_id = $1
+            and (folder = $2 or substr(folder, 1, length($2) + 1) = $2 || '/')",
+            owner_id,
+            folder
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Whether the folder of the owner has any photo that is not trashed
+    pub async fn has_photos(
+        &self,
+        owner_id: impl AsRef<str>,
+        folder: impl AsRef<str>,
+    ) -> Result<bool, ErrorResponse> {
+        let owner_id = owner_id.as_ref();
+        let folder = folder.as_ref();
+        query_scalar!(
+            r#"select exists(select 1 from photos where user_id = $1 and folder = $2 and trashed_at is null) as "exists!: bool""#,
+            owner_id,
+            folder
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    ///
+    /// Renames the folder and its subfolders in all the photos, shares and share links,
+    /// in a single transaction
+    ///
+    pub async fn rename_folder(
+        &self,
+        owner_id: impl AsRef<str>,
+        folder: impl AsRef<str>,
+        new_name: impl AsRef<str>,
+    ) -> Result<(), ErrorResponse> {
+        let owner_id = owner_id.as_ref();
+        let folder = folder.as_ref();
+        let new_name = new_name.as_ref();
+        let mut tx = self.pool.begin().await.map_err(internal_error)?;
+
+        query!(
+            "update photos set folder = $3 || substr(folder, length($2) + 1) where user_id = $1
+            and (folder = $2 or substr(folder, 1, length($2) + 1) = $2 || '/')",
+            owner_id,
+            folder,
+            new_name
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+        query!(
+            "update photo_shares set folder = $3 || substr(folder, length($2) + 1) where owner_id = $1
+            and (folder = $2 or substr(folder, 1, length($2) + 1) = $2 || '/')",
+            owner_id,
+            folder,
+            new_name
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+        query!(
+            "update share_links set folder = $3 || substr(folder, length($2) + 1) where user_id = $1
+            and (folder = $2 or substr(folder, 1, length($2) + 1) = $2 || '/')",
+            owner_id,
+            folder,
+            new_name
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+
+        tx.commit().await.map_err(internal_error)
+    }
+
+    ///
+    /// Moves the photos into the target folder with their new names and removes the shares
+    /// and share links of the merged folder, in a single transaction
+    ///
+    pub async fn merge_folder(
+        &self,
+        owner_id: impl AsRef<str>,
+        folder: impl AsRef<str>,
+        target: impl AsRef<str>,
+        photos: &[(i64, String)],
+    ) -> Result<(), ErrorResponse> {
+        let owner_id = owner_id.as_ref();
+        let folder = folder.as_ref();
+        let target = target.as_ref();
+        let mut tx = self.pool.begin().await.map_err(internal_error)?;
+
+        for (photo_id, name) in photos {
+            query!(
+                "update photos set folder = $2, name = $3 where id = $1",
+                photo_id,
+                target,
+                name
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+        }
+        delete_folder_access(&mut tx, owner_id, folder)
+            .await
+            .map_err(internal_error)?;
+
+        tx.commit().await.map_err(internal_error)
+    }
+
+    /// Removes the shares and share links of a folder that no longer exists
+    pub async fn delete_folder(
+        &self,
+        owner_id: impl AsRef<str>,
+        folder: impl AsRef<str>,
+    ) -> Result<(), ErrorResponse> {
+        let mut tx = self.pool.begin().await.map_err(internal_error)?;
+
+        delete_folder_access(&mut tx, owner_id.as_ref(), folder.as_ref())
+            .await
+            .map_err(internal_error)?;
+
+        tx.commit().await.map_err(internal_error)
+    }
+}
+
+async fn delete_folder_access(
+    connection: &mut SqliteConnection,
+    owner_id: &str,
+    folder: &str,
+) -> Result<(), sqlx::Error> {
+    query!(
+        "delete from photo_shares where owner_id = $1 and folder = $2",
+        owner_id,
+        folder
+    )
+    .execute(&mut *connection)
+    .await?;
+    query!(
+        "delete from share_links where user_id = $1 and folder = $2",
+        owner_id,
+        folder
+    )
+    .execute(&mut *connection)
+    .await?;
+
+    Ok(())
+}
@@ -1,2 +1,10 @@
+pub mod albums_repo;
+pub mod comments_repo;
+pub mod folders_repo;
 pub mod photos_repo;
+pub mod ratings_repo;
+pub mod share_links_repo;
+pub mod shares_repo;
+pub mod tags_repo;
+pub mod uploads_repo;
 pub mod users_repo;
@@ -1,8 +1,55 @@
-use crate::model::photo::{Photo, PhotoBase, PhotoBody};
+use crate::model::photo::{Photo, PhotoBase, PhotoBody, PhotoCursor};
+use crate::model::photo_changes::PhotoChanges;
+use crate::model::photo_location::{BoundingBox, PhotoCluster, PhotoLocation};
+use crate::model::photo_metadata::PhotoMetadata;
+use crate::model::photo_rating::ColorLabel;
+use crate::model::place::{Place, PlaceGroup, PlaceLevel};
+use crate::model::timeline::{TimelineBucket, TimelineGranularity};
 use crate::model::user::PUBLIC_USER_ID;
 use crate::utils::internal_error;
 use axum::response::ErrorResponse;
-use sqlx::{query, query_as, QueryBuilder, Sqlite, SqlitePool};
+use serde::Deserialize;
+use sqlx::{query, query_as, query_scalar, QueryBuilder, Sqlite, SqliteConnection, SqlitePool};
+use std::collections::HashSet;
+use time::OffsetDateTime;
+
+/// Optional restrictions applied when listing photos, all of them must match
+#[derive(Debug, Default)]
+pub struct PhotosFilter {
+    pub since: Option<OffsetDateTime>,
+    pub until: Option<OffsetDateTime>,
+    pub folder: Option<String>,
+    /// Case insensitive substring of the file name
+    pub name: Option<String>,
+    /// Case insensitive substring of the camera model in the photo's metadata
+    pub camera_model: Option<String>,
+    pub media_type: Option<MediaType>,
+    /// Only the photos the user marked as favorite
+    pub favorites_only: bool,
+    /// Only the photos with this tag, case insensitive
+    pub tag: Option<String>,
+    /// Only the photos the user rated with at least this many stars
+    pub min_rating: Option<i64>,
+    /// Only the photos the user labeled with this color
+    pub color_label: Option<ColorLabel>,
+    /// In bytes
+    pub min_file_size: Option<i64>,
+    pub country: Option<String>,
+    pub region: Option<String>,
+    pub city: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Deserialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MediaType {
+    Photo,
+    Video,
+}
+
+/// Files with these extensions are videos, everything else is treated as a photo
+const VIDEO_EXTENSIONS: [&str; 12] = [
+    "mp4", "m4v", "mov", "3gp", "mkv", "webm", "avi", "wmv", "mpg", "mpeg", "mts", "m2ts",
+];
 
 #[derive(Clone)]
 pub struct PhotosRepository {
@@ -15,17 +62,57 @@ impl PhotosRepository {
     }
 
     pub async fn get_photo(&self, id: i64) -> Result<Photo, ErrorResponse> {
+        query_as!(
+            Photo,
+            "select * from photos where id = $1 and trashed_at is null",
+            id
+        )
+        .fetch_one(&self.pool) // fetch_optional
+        .await
+        .map_err(internal_error)
+    }
+
+    pub async fn get_photo_including_trashed(&self, id: i64) -> Result<Photo, ErrorResponse> {
         query_as!(Photo, "select * from photos where id = $1", id)
-            .fetch_one(&self.pool) // fetch_optional
+            .fetch_one(&self.pool)
             .await
             .map_err(internal_error)
     }
 
+    /// Returns the photos with the given ids, ids that don't exist are skipped
+    pub async fn get_photos(&self, photo_ids: &[i64]) -> Result<Vec<Photo>, ErrorResponse> {
+        let mut photos = Vec::with_capacity(photo_ids.len());
+
+        for chunk in photo_ids.chunks(512) {
+            let mut query_builder: QueryBuilder<Sqlite> =
+                QueryBuilder::new("select * from photos where trashed_at is null and id in (");
+
+            let mut separated = query_builder.separated(", ");
+            for photo_id in chunk {
+                separated.push_bind(photo_id);
+            }
+            separated.push_unseparated(")");
+
+            photos.extend(
+                query_builder
+                    .build_query_as::<Photo>()
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(internal_error)?,
+            );
+        }
+
+        Ok(photos)
+    }
+
     pub async fn get_all_photos(&self) -> Result<Vec<Photo>, ErrorResponse> {
-        query_as!(Photo, "select * from photos order by created_at desc")
-            .fetch_all(&self.pool)
-            .await
-            .map_err(internal_error)
+        query_as!(
+            Photo,
+            "select * from photos where trashed_at is null order by created_at desc"
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
     }
 
     pub async fn get_favorite_photos(
@@ -50,7 +137,7 @@ impl PhotosRepository {
         let user_id = user_id.as_ref();
         query_as!(
             Photo,
-            "select * from photos where photos.user_id = $1 order by photos.created_at desc",
+            "select * from photos where photos.user_id = $1 and trashed_at is null order by photos.created_at desc",
             user_id
         )
         .fetch_all(&self.pool)
@@ -58,14 +145,158 @@ impl PhotosRepository {
         .map_err(internal_error)
     }
 
-    pub async fn get_photos_by_user_and_public(
+    /// Returns up to `limit` photos visible to the user, newest first, starting after the given `cursor`
+    pub async fn get_photos_page(
+        &self,
+        user_id: impl AsRef<str>,
+        filter: &PhotosFilter,
+        cursor: Option<PhotoCursor>,
+        limit: u32,
+    ) -> Result<Vec<Photo>, ErrorResponse> {
+        let mut query_builder = filtered_photos_query(user_id.as_ref(), filter);
+
+        if let Some(cursor) = cursor {
+            query_builder
+                .push(" and (created_at < ")
+                .push_bind(cursor.created_at)
+                .push(" or (created_at = ")
+                .push_bind(cursor.created_at)
+                .push(" and id < ")
+                .push_bind(cursor.id)
+                .push("))");
+        }
+
+        query_builder
+            .push(" order by created_at desc, id desc limit ")
+            .push_bind(limit);
+
+        query_builder
+            .build_query_as::<Photo>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(internal_error)
+    }
+
+    /// Returns the photos visible to the user matching both the text and the filter,
+    /// the most relevant first
+    pub async fn search_photos(
+        &self,
+        user_id: impl AsRef<str>,
+        text: &str,
+        filter: &PhotosFilter,
+        limit: u32,
+    ) -> Result<Vec<Photo>, ErrorResponse> {
+        let Some(text_query) = full_text_query(text) else {
+            return Ok(Vec::new());
+        };
+
+        let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(
+            "with matches as (select rowid, rank from photos_fts where photos_fts match ",
+        );
+        query_builder
+            .push_bind(text_query)
+            .push(") select photos.* from photos join matches on matches.rowid = photos.id where ");
+        push_filters(&mut query_builder, user_id.as_ref(), filter);
+        query_builder
+            .push(" order by matches.rank, created_at desc limit ")
+            .push_bind(limit);
+
+        query_builder
+            .build_query_as::<Photo>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(internal_error)
+    }
+
+    /// Counts the photos visible to the user created in each year, month or day, newest first
+    pub async fn get_timeline(
+        &self,
+        user_id: impl AsRef<str>,
+        granularity: TimelineGranularity,
+    ) -> Result<Vec<TimelineBucket>, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        let granularity = granularity.as_str();
+        query_as!(
+            TimelineBucket,
+            r#"select substr(start, 1, case $2 when 'year' then 4 when 'month' then 7 else 10 end) as "key!: String",
+            count as "count!: i64",
+            cast(strftime('%s', start) as integer) as "start!: i64",
+            cast(strftime('%s', start, '+1 ' || $2) as integer) as "end!: i64" from (
+                select case $2
+                    when 'year' then date(created_at, 'start of year')
+                    when 'month' then date(created_at, 'start of month')
+                    else date(created_at) end as start,
+                count(*) as count
+                from photos where trashed_at is null
+                and (user_id in ($1, $3) or id in (select photo_id from shared_photos where user_id = $1))
+                group by start
+            ) order by start desc"#,
+            user_id,
+            granularity,
+            PUBLIC_USER_ID
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Returns the photos visible to the user created before the given time on any of
+    /// the `MM-DD` days of the year
+    pub async fn get_photos_on_days(
+        &self,
+        user_id: impl AsRef<str>,
+        month_days: &[String],
+        before: OffsetDateTime,
+    ) -> Result<Vec<Photo>, ErrorResponse> {
+        if month_days.is_empty() {
+            return Ok(Vec::new());
+        }
+
+        let filter = PhotosFilter {
+            until: Some(before),
+            ..Default::default()
+        };
+        let mut query_builder = filtered_photos_query(user_id.as_ref(), &filter);
+
+        query_builder.push(" and strftime('%m-%d', created_at) in (");
+        let mut separated = query_builder.separated(", ");
+        for month_day in month_days {
+            separated.push_bind(month_day);
+        }
+        query_builder.push(") order by created_at desc");
+
+        query_builder
+            .build_query_as::<Photo>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(internal_error)
+    }
+
+    /// Returns all the photos visible to the user matching the filter, newest first
+    pub async fn get_filtered_photos(
+        &self,
+        user_id: impl AsRef<str>,
+        filter: &PhotosFilter,
+    ) -> Result<Vec<Photo>, ErrorResponse> {
+        let mut query_builder = filtered_photos_query(user_id.as_ref(), filter);
+        query_builder.push(" order by created_at desc, id desc");
+
+        query_builder
+            .build_query_as::<Photo>()
+            .fetch_all(&self.pool)
+            .await
+            .map_err(internal_error)
+    }
+
+    /// Returns the trashed photos visible to the user, most recently trashed first
+    pub async fn get_trashed_photos(
         &self,
         user_id: impl AsRef<str>,
     ) -> Result<Vec<Photo>, ErrorResponse> {
         let user_id = user_id.as_ref();
         query_as!(
             Photo,
-            "select * from photos where user_id = $1 or user_id = $2 order by created_at desc",
+            "select * from photos where user_id in ($1, $2) and trashed_at is not null order by trashed_at desc",
             user_id,
             PUBLIC_USER_ID
         )
@@ -74,40 +305,463 @@ impl PhotosRepository {
         .map_err(internal_error)
     }
 
-    pub async fn insert_photo(&self, photo: &PhotoBody) -> Result<Photo, ErrorResponse> {
-        let user_id = photo.user_id();
-        let name = photo.name();
-        let created_at = photo.created_at();
-        let file_size = photo.file_size();
-        let folder_name = photo.folder_name();
+    /// Returns the photos owned by the user that are trashed
+    pub async fn get_user_trashed_photos(
+        &self,
+        user_id: impl AsRef<str>,
+    ) -> Result<Vec<Photo>, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        query_as!(
+            Photo,
+            "select * from photos where user_id = $1 and trashed_at is not null",
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
 
+    /// Returns the photos trashed before the given time which nobody marked as favorite
+    pub async fn get_expired_trash(
+        &self,
+        trashed_before: OffsetDateTime,
+    ) -> Result<Vec<Photo>, ErrorResponse> {
         query_as!(
             Photo,
-            "insert into photos (user_id, name, created_at, file_size, folder) values ($1, $2, $3, $4, $5) returning *",
+            "select * from photos where trashed_at < $1 and id not in (select photo_id from favorite_photos)",
+            trashed_before
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Returns the names of the trashed photos of the user in the folder, `None` being the root
+    pub async fn get_trashed_names(
+        &self,
+        user_id: impl AsRef<str>,
+        folder: Option<&str>,
+    ) -> Result<Vec<String>, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        query_scalar!(
+            "select name from photos where user_id = $1 and folder is $2 and trashed_at is not null",
             user_id,
-            name,
-            created_at,
-            file_size,
-            folder_name
+            folder
         )
-        .fetch_one(&self.pool)
+        .fetch_all(&self.pool)
         .await
         .map_err(internal_error)
     }
 
+    /// Folds the change journal into the last state of every photo visible to the user
+    /// that changed after the `since` token.
+    /// The changes to the photos shared with this user are journaled for it as well,
+    /// whether a photo is upserted or deleted only depends on it being visible now.
+    pub async fn get_changes(
+        &self,
+        user_id: impl AsRef<str>,
+        since: i64,
+    ) -> Result<PhotoChanges, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        // A single transaction ensures all the queries see the same snapshot of the journal
+        let mut tx = self.pool.begin().await.map_err(internal_error)?;
+
+        let token = query_scalar!("select coalesce(max(id), 0) from photo_changes")
+            .fetch_one(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+
+        let upserted = query_as!(
+            Photo,
+            "select * from photos where trashed_at is null
+            and (user_id in ($2, $3) or id in (select photo_id from shared_photos where user_id = $2))
+            and id in (
+                select photo_id from photo_changes
+                where id > $1 and kind in ('upsert', 'delete') and user_id in ($2, $3)
+            )",
+            since,
+            user_id,
+            PUBLIC_USER_ID
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+
+        // Anything that changed but is no longer visible is reported as deleted
+        let upserted_ids: HashSet<i64> = upserted.iter().map(|photo| photo.id).collect();
+        let deleted = query_scalar!(
+            "select distinct photo_id from photo_changes
+            where id > $1 and kind in ('upsert', 'delete') and user_id in ($2, $3)",
+            since,
+            user_id,
+            PUBLIC_USER_ID
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(internal_error)?
+        .into_iter()
+        .filter(|photo_id| !upserted_ids.contains(photo_id))
+        .collect();
+
+        let favorite_changes = query!(
+            "select photo_id, kind from photo_changes where id in (
+                select max(id) from photo_changes
+                where id > $1 and user_id = $2 and kind in ('favorite', 'unfavorite')
+                group by photo_id
+            )",
+            since,
+            user_id
+        )
+        .fetch_all(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+
+        let (favorited, unfavorited): (Vec<_>, Vec<_>) = favorite_changes
+            .into_iter()
+            .partition(|record| record.kind == "favorite");
+
+        Ok(PhotoChanges {
+            upserted,
+            deleted,
+            favorited: favorited
+                .into_iter()
+                .map(|record| record.photo_id)
+                .collect(),
+            unfavorited: unfavorited
+                .into_iter()
+                .map(|record| record.photo_id)
+                .collect(),
+            token,
+        })
+    }
+
+    /// Returns a photo visible to the user (its own or a public one) with the given content hash
+    pub async fn get_photo_by_hash(
+        &self,
+        user_id: impl AsRef<str>,
+        content_hash: impl AsRef<str>,
+    ) -> Result<Option<Photo>, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        let content_hash = content_hash.as_ref();
+        query_as!(
+            Photo,
+            "select * from photos where content_hash = $1 and user_id in ($2, $3) and trashed_at is null limit 1",
+            content_hash,
+            user_id,
+            PUBLIC_USER_ID
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Returns which of the given content hashes belong to photos visible to the user
+    pub async fn get_existing_hashes(
+        &self,
+        user_id: impl AsRef<str>,
+        content_hashes: &[String],
+    ) -> Result<Vec<String>, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        let mut existing = Vec::new();
+
+        for chunk in content_hashes.chunks(512) {
+            let mut query_builder: QueryBuilder<Sqlite> =
+                QueryBuilder::new(
+                "select distinct content_hash from photos where trashed_at is null and user_id in (",
+            );
+            query_builder
+                .push_bind(user_id)
+                .push(", ")
+                .push_bind(PUBLIC_USER_ID)
+                .push(") and content_hash in (");
+
+            let mut separated = query_builder.separated(", ");
+            for content_hash in chunk {
+                separated.push_bind(content_hash);
+            }
+            separated.push_unseparated(")");
+
+            existing.extend(
+                query_builder
+                    .build_query_scalar::<String>()
+                    .fetch_all(&self.pool)
+                    .await
+                    .map_err(internal_error)?,
+            );
+        }
+
+        Ok(existing)
+    }
+
+    /// Inserts the photo along with its metadata, if it has any
+    pub async fn insert_photo(&self, photo: &PhotoBody) -> Result<Photo, ErrorResponse> {
+        let mut tx = self.pool.begin().await.map_err(internal_error)?;
+
+        let inserted = insert_photo_body(&mut tx, photo)
+            .await
+            .map_err(internal_error)?;
+
+        tx.commit().await.map_err(internal_error)?;
+        Ok(inserted)
+    }
+
     pub async fn insert_photos(&self, photos: &[PhotoBody]) -> Result<(), sqlx::Error> {
-        let mut query_builder: QueryBuilder<Sqlite> =
-            QueryBuilder::new("insert into photos (user_id, name, created_at, file_size, folder) ");
-
-        query_builder.push_values(photos, |mut b, photo| {
-            b.push_bind(photo.user_id())
-                .push_bind(photo.name())
-                .push_bind(photo.created_at())
-                .push_bind(photo.file_size())
-                .push_bind(photo.folder_name());
-        });
+        let mut tx = self.pool.begin().await?;
+
+        for photo in photos {
+            insert_photo_body(&mut tx, photo).await?;
+        }
 
-        query_builder.build().execute(&self.pool).await.map(|_| ())
+        tx.commit().await
+    }
+
+    pub async fn get_photo_metadata(
+        &self,
+        photo_id: i64,
+    ) -> Result<Option<PhotoMetadata>, ErrorResponse> {
+        query_as!(
+            PhotoMetadata,
+            "select camera_make, camera_model, lens_model, exposure_time, f_number, iso, focal_length,
+            width, height, orientation, latitude, longitude, altitude, city, region, country, geocoded_with
+            from photo_metadata where photo_id = $1",
+            photo_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    pub async fn get_caption(&self, photo_id: i64) -> Result<Option<String>, ErrorResponse> {
+        query_scalar!(
+            "select caption from photo_captions where photo_id = $1",
+            photo_id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Replaces the caption of the photo, `None` removes it
+    pub async fn set_caption(
+        &self,
+        photo_id: i64,
+        caption: Option<&str>,
+    ) -> Result<(), ErrorResponse> {
+        match caption {
+            Some(caption) => {
+                query!(
+                    "insert into photo_captions (photo_id, caption) values ($1, $2)
+                on conflict (photo_id) do update set caption = excluded.caption",
+                    photo_id,
+                    caption
+                )
+                .execute(&self.pool)
+                .await
+            }
+            None => {
+                query!("delete from photo_captions where photo_id = $1", photo_id)
+                    .execute(&self.pool)
+                    .await
+            }
+        }
+        .map(|_| ())
+        .map_err(internal_error)
+    }
+
+    /// Photos without a caption, which are not in the trash
+    pub async fn get_photos_without_caption(&self) -> Result<Vec<Photo>, sqlx::Error> {
+        query_as!(
+            Photo,
+            "select * from photos where trashed_at is null and id not in (select photo_id from photo_captions)"
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Sets the captions of the photos, unless they were given one in the meantime
+    pub async fn insert_captions(&self, captions: &[(i64, String)]) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        for (photo_id, caption) in captions {
+            query!(
+                "insert into photo_captions (photo_id, caption) values ($1, $2)
+                on conflict (photo_id) do nothing",
+                photo_id,
+                caption
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await
+    }
+
+    /// Photos added before their metadata was stored in the database, trashed ones included
+    pub async fn get_photos_without_metadata(&self) -> Result<Vec<Photo>, sqlx::Error> {
+        query_as!(
+            Photo,
+            "select * from photos where id not in (select photo_id from photo_metadata)"
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Groups the geotagged photos visible to the user inside the area by the square cell
+    /// of the given size, in degrees, they fall into
+    pub async fn get_photo_clusters(
+        &self,
+        user_id: impl AsRef<str>,
+        area: &BoundingBox,
+        cell_size: f64,
+    ) -> Result<Vec<PhotoCluster>, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        // The bare id column is taken from the row with the max created_at
+        query_as!(
+            PhotoCluster,
+            r#"select count as "count!: i64", latitude as "latitude!: f64", longitude as "longitude!: f64",
+            photo_id as "photo_id!: i64" from (
+                select count(*) as count, avg(m.latitude) as latitude, avg(m.longitude) as longitude,
+                p.id as photo_id, max(p.created_at)
+                from photo_metadata m join photos p on p.id = m.photo_id
+                where p.trashed_at is null
+                and (p.user_id in ($1, $7) or p.id in (select photo_id from shared_photos where user_id = $1))
+                and m.latitude between $2 and $3
+                and (case when $4 <= $5 then m.longitude between $4 and $5 else m.longitude >= $4 or m.longitude <= $5 end)
+                group by cast((m.longitude + 180) / $6 as integer), cast((m.latitude + 90) / $6 as integer)
+            )"#,
+            user_id,
+            area.south,
+            area.north,
+            area.west,
+            area.east,
+            cell_size,
+            PUBLIC_USER_ID
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Returns the location of the newest geotagged photos visible to the user inside the area
+    pub async fn get_photo_locations(
+        &self,
+        user_id: impl AsRef<str>,
+        area: &BoundingBox,
+        limit: u32,
+    ) -> Result<Vec<PhotoLocation>, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        query_as!(
+            PhotoLocation,
+            r#"select m.photo_id, m.latitude as "latitude!: f64", m.longitude as "longitude!: f64"
+            from photo_metadata m join photos p on p.id = m.photo_id
+            where p.trashed_at is null
+            and (p.user_id in ($1, $7) or p.id in (select photo_id from shared_photos where user_id = $1))
+            and m.latitude between $2 and $3
+            and (case when $4 <= $5 then m.longitude between $4 and $5 else m.longitude >= $4 or m.longitude <= $5 end)
+            order by p.created_at desc limit $6"#,
+            user_id,
+            area.south,
+            area.north,
+            area.west,
+            area.east,
+            limit,
+            PUBLIC_USER_ID
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Returns the id and coordinates of the geotagged photos whose place is not known yet
+    /// Locations that were not looked up in this version of the GeoNames dump and have no place
+    pub async fn get_unplaced_locations(
+        &self,
+        geocoder_version: &str,
+    ) -> Result<Vec<PhotoLocation>, sqlx::Error> {
+        query_as!(
+            PhotoLocation,
+            r#"select photo_id, latitude as "latitude!: f64", longitude as "longitude!: f64"
+            from photo_metadata where latitude is not null and longitude is not null and country is null
+            and geocoded_with is not $1"#,
+            geocoder_version
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Sets the place found for each photo, if any, marking them as looked up in this version
+    /// of the GeoNames dump
+    pub async fn update_places(
+        &self,
+        places: &[(i64, Option<Place>)],
+        geocoder_version: &str,
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        for (photo_id, place) in places {
+            let city = place.as_ref().map(|place| &place.city);
+            let region = place.as_ref().and_then(|place| place.region.as_ref());
+            let country = place.as_ref().map(|place| &place.country);
+            query!(
+                "update photo_metadata set city = $2, region = $3, country = $4, geocoded_with = $5
+                where photo_id = $1",
+                photo_id,
+                city,
+                region,
+                country,
+                geocoder_version
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await
+    }
+
+    /// Groups the photos visible to the user by the place they were taken in, the places
+    /// with the most photos first
+    pub async fn get_place_groups(
+        &self,
+        user_id: impl AsRef<str>,
+        level: PlaceLevel,
+    ) -> Result<Vec<PlaceGroup>, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        let level = level.as_str();
+        // The bare id column is taken from the row with the max created_at
+        query_as!(
+            PlaceGroup,
+            r#"select country as "country!: String", region as "region?: String", city as "city?: String",
+            count as "count!: i64", photo_id as "photo_id!: i64" from (
+                select m.country as country,
+                case when $2 != 'country' then m.region end as region,
+                case when $2 = 'city' then m.city end as city,
+                count(*) as count, p.id as photo_id, max(p.created_at)
+                from photo_metadata m join photos p on p.id = m.photo_id
+                where p.trashed_at is null and m.country is not null
+                and (p.user_id in ($1, $3) or p.id in (select photo_id from shared_photos where user_id = $1))
+                group by 1, 2, 3
+            ) order by count desc, country, region, city"#,
+            user_id,
+            level,
+            PUBLIC_USER_ID
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Stores the metadata of the photos, replacing the existing one
+    pub async fn insert_metadata(
+        &self,
+        metadata: &[(i64, PhotoMetadata)],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        for (photo_id, metadata) in metadata {
+            insert_photo_metadata(&mut tx, *photo_id, metadata).await?;
+        }
+
+        tx.commit().await
     }
 
     pub async fn insert_favorite<T: AsRef<str>>(
@@ -144,53 +798,390 @@ impl PhotosRepository {
         .map_err(internal_error)
     }
 
+    /// Marks all the photos as favorite in a single transaction, ignoring those that already are
+    pub async fn insert_favorites<T: AsRef<str>>(
+        &self,
+        photo_ids: &[i64],
+        user_id: T,
+    ) -> Result<(), ErrorResponse> {
+        let user_id = user_id.as_ref();
+        let mut tx = self.pool.begin().await.map_err(internal_error)?;
+
+        for photo_id in photo_ids {
+            query!(
+                "insert or ignore into favorite_photos (photo_id, user_id) values ($1, $2)",
+                photo_id,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+        }
+
+        tx.commit().await.map_err(internal_error)
+    }
+
+    /// Unmarks all the photos as favorite in a single transaction
+    pub async fn delete_favorites<T: AsRef<str>>(
+        &self,
+        photo_ids: &[i64],
+        user_id: T,
+    ) -> Result<(), ErrorResponse> {
+        let user_id = user_id.as_ref();
+        let mut tx = self.pool.begin().await.map_err(internal_error)?;
+
+        for photo_id in photo_ids {
+            query!(
+                "delete from favorite_photos where photo_id = $1 and user_id = $2",
+                photo_id,
+                user_id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+        }
+
+        tx.commit().await.map_err(internal_error)
+    }
+
     pub async fn update_photo(&self, photo: &Photo) -> Result<(), ErrorResponse> {
-        let photo_id = photo.id;
-        let user_id = photo.user_id();
-        let name = photo.name();
-        let created_at = photo.created_at();
-        let file_size = photo.file_size();
-        let folder_name = photo.folder_name();
+        self.update_photos(std::slice::from_ref(photo)).await
+    }
 
-        query!(
-            "update photos set user_id = $2, name = $3, created_at = $4, file_size = $5, folder = $6 where id = $1",
-            photo_id,
-            user_id,
-            name,
-            created_at,
-            file_size,
-            folder_name
-        )
-            .execute(&self.pool)
+    /// Updates all the photos in a single transaction
+    pub async fn update_photos(&self, photos: &[Photo]) -> Result<(), ErrorResponse> {
+        let mut tx = self.pool.begin().await.map_err(internal_error)?;
+
+        for photo in photos {
+            let photo_id = photo.id;
+            let user_id = photo.user_id();
+            let name = photo.name();
+            let created_at = photo.created_at();
+            let file_size = photo.file_size();
+            let folder_name = photo.folder_name();
+            let content_hash = photo.content_hash();
+            let trashed_at = photo.trashed_at;
+
+            query!(
+                "update photos set user_id = $2, name = $3, created_at = $4, file_size = $5, folder = $6, content_hash = $7, trashed_at = $8 where id = $1",
+                photo_id,
+                user_id,
+                name,
+                created_at,
+                file_size,
+                folder_name,
+                content_hash,
+                trashed_at
+            )
+            .execute(&mut *tx)
             .await
-            .map(|_| ())
-            .map_err(internal_error)
+            .map_err(internal_error)?;
+        }
+
+        tx.commit().await.map_err(internal_error)
     }
 
-    pub async fn delete_photo(&self, id: i64) -> Result<(), ErrorResponse> {
-        query!("delete from photos where id = $1", id)
-            .execute(&self.pool)
+    /// Marks all the photos as trashed in a single transaction
+    pub async fn trash_photos(
+        &self,
+        photo_ids: &[i64],
+        trashed_at: OffsetDateTime,
+    ) -> Result<(), ErrorResponse> {
+        let mut tx = self.pool.begin().await.map_err(internal_error)?;
+
+        for photo_id in photo_ids {
+            query!(
+                "update photos set trashed_at = $2 where id = $1",
+                photo_id,
+                trashed_at
+            )
+            .execute(&mut *tx)
             .await
-            .map(|_| ())
-            .map_err(internal_error)
+            .map_err(internal_error)?;
+        }
+
+        tx.commit().await.map_err(internal_error)
+    }
+
+    /// Sets the content hash of multiple photos given as `(id, content_hash)` pairs
+    pub async fn update_content_hashes(&self, hashes: &[(i64, String)]) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        for (photo_id, content_hash) in hashes {
+            query!(
+                "update photos set content_hash = $2 where id = $1",
+                photo_id,
+                content_hash
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await
     }
 
+    /// Deletes the photos and the favorite marks referencing them in a single transaction
     pub async fn delete_photos(&self, photo_ids: &[i64]) -> Result<(), sqlx::Error> {
-        if photo_ids.is_empty() {
-            return Ok(());
+        let mut tx = self.pool.begin().await?;
+
+        for chunk in photo_ids.chunks(512) {
+            for table in [
+                "delete from favorite_photos where photo_id in (",
+                "delete from photos where id in (",
+            ] {
+                let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new(table);
+
+                // One element vector is handled correctly but an empty vector
+                // would cause a sql syntax error
+                let mut separated = query_builder.separated(", ");
+                for photos in chunk.iter() {
+                    separated.push_bind(photos);
+                }
+                separated.push_unseparated(") ");
+
+                query_builder.build().execute(&mut *tx).await?;
+            }
         }
 
-        let mut query_builder: QueryBuilder<Sqlite> =
-            QueryBuilder::new("delete from photos where id in (");
+        tx.commit().await
+    }
+}
 
-        // One element vector is handled correctly but an empty vector
-        // would cause a sql syntax error
-        let mut separated = query_builder.separated(", ");
-        for photos in photo_ids.iter() {
-            separated.push_bind(photos);
+/// Restricts the query to the photos visible to the user: its own photos, the public ones
+/// and the ones shared with it
+fn push_visible_to<'a>(query_builder: &mut QueryBuilder<'a, Sqlite>, user_id: &'a str) {
+    query_builder
+        .push("(user_id in (")
+        .push_bind(user_id)
+        .push(", ")
+        .push_bind(PUBLIC_USER_ID)
+        .push(") or id in (select photo_id from shared_photos where user_id = ")
+        .push_bind(user_id)
+        .push("))");
+}
+
+/// Selects the photos visible to the user that match the filter
+fn filtered_photos_query<'a>(
+    user_id: &'a str,
+    filter: &'a PhotosFilter,
+) -> QueryBuilder<'a, Sqlite> {
+    let mut query_builder: QueryBuilder<Sqlite> = QueryBuilder::new("select * from photos where ");
+    push_filters(&mut query_builder, user_id, filter);
+
+    query_builder
+}
+
+/// Pushes the conditions selecting the photos visible to the user that are not trashed
+/// and match the filter
+fn push_filters<'a>(
+    query_builder: &mut QueryBuilder<'a, Sqlite>,
+    user_id: &'a str,
+    filter: &'a PhotosFilter,
+) {
+    query_builder.push("trashed_at is null and ");
+    push_visible_to(query_builder, user_id);
+
+    if let Some(since) = filter.since {
+        query_builder.push(" and created_at >= ").push_bind(since);
+    }
+    if let Some(until) = filter.until {
+        query_builder.push(" and created_at < ").push_bind(until);
+    }
+    if let Some(folder) = &filter.folder {
+        query_builder.push(" and folder = ").push_bind(folder);
+    }
+    if let Some(name) = &filter.name {
+        query_builder
+            .push(" and name like ")
+            .push_bind(contains_pattern(name))
+            .push(" escape '\\'");
+    }
+    if let Some(camera_model) = &filter.camera_model {
+        query_builder
+            .push(" and id in (select photo_id from photo_metadata where camera_model like ")
+            .push_bind(contains_pattern(camera_model))
+            .push(" escape '\\')");
+    }
+    if let Some(media_type) = filter.media_type {
+        query_builder.push(match media_type {
+            MediaType::Photo => " and not (",
+            MediaType::Video => " and (",
+        });
+        let mut separated = query_builder.separated(" or ");
+        for extension in VIDEO_EXTENSIONS {
+            separated
+                .push("lower(name) like ")
+                .push_bind_unseparated(format!("%.{extension}"));
         }
-        separated.push_unseparated(") ");
+        query_builder.push(")");
+    }
+    if filter.favorites_only {
+        query_builder
+            .push(" and id in (select photo_id from favorite_photos where user_id = ")
+            .push_bind(user_id)
+            .push(")");
+    }
+    if let Some(tag) = &filter.tag {
+        query_builder
+            .push(" and id in (select photo_id from photo_tags join tags on tags.id = photo_tags.tag_id where tags.name = ")
+            .push_bind(tag)
+            .push(")");
+    }
+    if let Some(min_rating) = filter.min_rating {
+        query_builder
+            .push(" and id in (select photo_id from photo_ratings where user_id = ")
+            .push_bind(user_id)
+            .push(" and rating >= ")
+            .push_bind(min_rating)
+            .push(")");
+    }
+    if let Some(color_label) = filter.color_label {
+        query_builder
+            .push(" and id in (select photo_id from photo_ratings where user_id = ")
+            .push_bind(user_id)
+            .push(" and color_label = ")
+            .push_bind(color_label)
+            .push(")");
+    }
+    if let Some(min_file_size) = filter.min_file_size {
+        query_builder
+            .push(" and file_size >= ")
+            .push_bind(min_file_size);
+    }
+    for (column, value) in [
+        ("country", &filter.country),
+        ("region", &filter.region),
+        ("city", &filter.city),
+    ] {
+        if let Some(value) = value {
+            query_builder
+                .push(format!(
+                    " and id in (select photo_id from photo_metadata where {column} = "
+                ))
+                .push_bind(value)
+                .push(" collate nocase)");
+        }
+    }
+}
+
+/// Turns the text typed by the user into an FTS5 query matching the photos that contain
+/// a word starting with each of the typed ones
+fn full_text_query(text: &str) -> Option<String> {
+    let words = text
+        .split_whitespace()
+        .map(|word| format!("\"{}\"*", word.replace('"', "\"\"")))
+        .collect::<Vec<_>>();
+
+    (!words.is_empty()).then(|| words.join(" "))
+}
+
+/// `like` pattern matching any text containing the value, its wildcards are escaped with `\`
+fn contains_pattern(value: &str) -> String {
+    let escaped = value
+        .replace('\\', "\\\\")
+        .replace('%', "\\%")
+        .replace('_', "\\_");
+    format!("%{escaped}%")
+}
+
+async fn insert_photo_body(
+    connection: &mut SqliteConnection,
+    photo: &PhotoBody,
+) -> Result<Photo, sqlx::Error> {
+    let user_id = photo.user_id();
+    let name = photo.name();
+    let created_at = photo.created_at();
+    let file_size = photo.file_size();
+    let folder_name = photo.folder_name();
+    let content_hash = photo.content_hash();
+
+    let inserted = query_as!(
+        Photo,
+        "insert into photos (user_id, name, created_at, file_size, folder, content_hash) values ($1, $2, $3, $4, $5, $6) returning *",
+        user_id,
+        name,
+        created_at,
+        file_size,
+        folder_name,
+        content_hash
+    )
+    .fetch_one(&mut *connection)
+    .await?;
+
+    if let Some(metadata) = photo.metadata() {
+        insert_photo_metadata(connection, inserted.id, metadata).await?;
+    }
+    if let Some(caption) = photo.caption() {
+        query!(
+            "insert or replace into photo_captions (photo_id, caption) values ($1, $2)",
+            inserted.id,
+            caption
+        )
+        .execute(&mut *connection)
+        .await?;
+    }
+    if let Some(rating) = photo.rating() {
+        query!(
+            "insert or replace into photo_ratings (photo_id, user_id, rating) values ($1, $2, $3)",
+            inserted.id,
+            user_id,
+            rating
+        )
+        .execute(&mut *connection)
+        .await?;
+    }
+
+    Ok(inserted)
+}
+
+async fn insert_photo_metadata(
+    connection: &mut SqliteConnection,
+    photo_id: i64,
+    metadata: &PhotoMetadata,
+) -> Result<(), sqlx::Error> {
+    query!(
+        "insert or replace into photo_metadata (photo_id, camera_make, camera_model, lens_model,
+        exposure_time, f_number, iso, focal_length, width, height, orientation, latitude, longitude, altitude,
+        city, region, country, geocoded_with)
+        values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10, $11, $12, $13, $14, $15, $16, $17, $18)",
+        photo_id,
+        metadata.camera_make,
+        metadata.camera_model,
+        metadata.lens_model,
+        metadata.exposure_time,
+        metadata.f_number,
+        metadata.iso,
+        metadata.focal_length,
+        metadata.width,
+        metadata.height,
+        metadata.orientation,
+        metadata.latitude,
+        metadata.longitude,
+        metadata.altitude,
+        metadata.city,
+        metadata.region,
+        metadata.country,
+        metadata.geocoded_with
+    )
+    .execute(connection)
+    .await
+    .map(|_| ())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
 
-        query_builder.build().execute(&self.pool).await.map(|_| ())
+    #[test]
+    fn full_text_queries() {
+        assert_eq!(
+            full_text_query("beach  2016"),
+            Some("\"beach\"* \"2016\"*".to_string())
+        );
+        assert_eq!(
+            full_text_query("IMG_2016 \"x"),
+            Some("\"IMG_2016\"* \"\"\"x\"*".to_string())
+        );
+        assert_eq!(full_text_query("   "), None);
     }
 }
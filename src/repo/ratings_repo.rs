@@ -0,0 +1,139 @@
+use crate::model::photo::Photo;
+use crate::model::photo_rating::{ColorLabel, PhotoRating};
+use crate::utils::internal_error;
+use axum::response::ErrorResponse;
+use sqlx::{query, query_as, SqliteConnection, SqlitePool};
+
+#[derive(Clone)]
+pub struct RatingsRepository {
+    pool: SqlitePool,
+}
+
+impl RatingsRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns the ratings and color labels the user gave to the photos
+    pub async fn get_ratings(
+        &self,
+        user_id: impl AsRef<str>,
+    ) -> Result<Vec<PhotoRating>, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        query_as!(
+            PhotoRating,
+            r#"select photo_id, rating, color_label as "color_label: ColorLabel"
+            from photo_ratings where user_id = $1"#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Sets the rating of the photos, keeping their color labels
+    pub async fn set_ratings(
+        &self,
+        user_id: impl AsRef<str>,
+        photo_ids: &[i64],
+        rating: i64,
+    ) -> Result<(), ErrorResponse> {
+        let user_id = user_id.as_ref();
+        let mut tx = self.pool.begin().await.map_err(internal_error)?;
+
+        for photo_id in photo_ids {
+            query!(
+                "insert into photo_ratings (photo_id, user_id, rating) values ($1, $2, $3)
+                on conflict (photo_id, user_id) do update set rating = excluded.rating",
+                photo_id,
+                user_id,
+                rating
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+        }
+        delete_unrated(&mut tx, user_id)
+            .await
+            .map_err(internal_error)?;
+
+        tx.commit().await.map_err(internal_error)
+    }
+
+    /// Sets the color label of the photos, keeping their ratings, `None` removes the label
+    pub async fn set_color_labels(
+        &self,
+        user_id: impl AsRef<str>,
+        photo_ids: &[i64],
+        color_label: Option<ColorLabel>,
+    ) -> Result<(), ErrorResponse> {
+        let user_id = user_id.as_ref();
+        let mut tx = self.pool.begin().await.map_err(internal_error)?;
+
+        for photo_id in photo_ids {
+            query!(
+                "insert into photo_ratings (photo_id, user_id, color_label) values ($1, $2, $3)
+                on conflict (photo_id, user_id) do update set color_label = excluded.color_label",
+                photo_id,
+                user_id,
+                color_label
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+        }
+        delete_unrated(&mut tx, user_id)
+            .await
+            .map_err(internal_error)?;
+
+        tx.commit().await.map_err(internal_error)
+    }
+    /// Returns the photos their owner didn't rate nor label, which are not in the trash
+    pub async fn get_photos_without_owner_rating(&self) -> Result<Vec<Photo>, sqlx::Error> {
+        query_as!(
+            Photo,
+            "select * from photos where trashed_at is null and not exists (
+                select 1 from photo_ratings r where r.photo_id = photos.id and r.user_id = photos.user_id
+            )"
+        )
+        .fetch_all(&self.pool)
+        .await
+    }
+
+    /// Sets the ratings their owner gave to the photos, `(photo_id, user_id, rating)`,
+    /// unless the owner rated or labeled them in the meantime
+    pub async fn insert_owner_ratings(
+        &self,
+        ratings: &[(i64, String, i64)],
+    ) -> Result<(), sqlx::Error> {
+        let mut tx = self.pool.begin().await?;
+
+        for (photo_id, user_id, rating) in ratings {
+            query!(
+                "insert into photo_ratings (photo_id, user_id, rating) values ($1, $2, $3)
+                on conflict (photo_id, user_id) do nothing",
+                photo_id,
+                user_id,
+                rating
+            )
+            .execute(&mut *tx)
+            .await?;
+        }
+
+        tx.commit().await
+    }
+}
+
+/// Rows with neither a rating nor a color label are not kept
+async fn delete_unrated(
+    connection: &mut SqliteConnection,
+    user_id: &str,
+) -> Result<(), sqlx::Error> {
+    query!(
+        "delete from photo_ratings where user_id = $1 and rating = 0 and color_label is null",
+        user_id
+    )
+    .execute(connection)
+    .await
+    .map(|_| ())
+}
@@ -0,0 +1,168 @@
+use crate::model::photo::Photo;
+use crate::model::share_link::ShareLink;
+use crate::model::user::PUBLIC_USER_ID;
+use crate::utils::internal_error;
+use axum::response::ErrorResponse;
+use sqlx::{query, query_as, SqlitePool};
+
+#[derive(Clone)]
+pub struct ShareLinksRepository {
+    pool: SqlitePool,
+}
+
+impl ShareLinksRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get_link(&self, id: i64) -> Result<Option<ShareLink>, ErrorResponse> {
+        query_as!(ShareLink, "select * from share_links where id = $1", id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal_error)
+    }
+
+    pub async fn get_link_by_token(
+        &self,
+        token: impl AsRef<str>,
+    ) -> Result<Option<ShareLink>, ErrorResponse> {
+        let token = token.as_ref();
+        query_as!(
+            ShareLink,
+            "select * from share_links where token = $1",
+            token
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Returns the links created by the user, newest first
+    pub async fn get_links(
+        &self,
+        user_id: impl AsRef<str>,
+    ) -> Result<Vec<ShareLink>, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        query_as!(
+            ShareLink,
+            "select * from share_links where user_id = $1 order by id desc",
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    ///
+    /// Returns the photos shared by the link, newest first.
+    /// Only the photos still visible to the link's creator (its own and the public ones) are shared.
+    ///
+    pub async fn get_link_photos(&self, link: &ShareLink) -> Result<Vec<Photo>, ErrorResponse> {
+        match &link.folder {
+            Some(folder) => query_as!(
+                Photo,
+                "select * from photos where user_id = $1 and folder = $2 and trashed_at is null
+                order by created_at desc, id desc",
+                link.user_id,
+                folder
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(internal_error),
+            None => query_as!(
+                Photo,
+                "select photos.* from share_link_photos
+                join photos on photos.id = share_link_photos.photo_id
+                where share_link_photos.link_id = $1 and photos.user_id in ($2, $3) and photos.trashed_at is null
+                order by photos.created_at desc, photos.id desc",
+                link.id,
+                link.user_id,
+                PUBLIC_USER_ID
+            )
+            .fetch_all(&self.pool)
+            .await
+            .map_err(internal_error),
+        }
+    }
+
+    /// Returns the photo if it is shared by the link
+    pub async fn get_link_photo(
+        &self,
+        link: &ShareLink,
+        photo_id: i64,
+    ) -> Result<Option<Photo>, ErrorResponse> {
+        match &link.folder {
+            Some(folder) => query_as!(
+                Photo,
+                "select * from photos where id = $1 and user_id = $2 and folder = $3 and trashed_at is null",
+                photo_id,
+                link.user_id,
+                folder
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal_error),
+            None => query_as!(
+                Photo,
+                "select photos.* from share_link_photos
+                join photos on photos.id = share_link_photos.photo_id
+                where share_link_photos.link_id = $1 and photos.id = $2
+                and photos.user_id in ($3, $4) and photos.trashed_at is null",
+                link.id,
+                photo_id,
+                link.user_id,
+                PUBLIC_USER_ID
+            )
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal_error),
+        }
+    }
+
+    /// Inserts the link along with the selected photos, if it doesn't share a folder
+    pub async fn insert_link(
+        &self,
+        link: &ShareLink,
+        photo_ids: &[i64],
+    ) -> Result<ShareLink, ErrorResponse> {
+        let mut tx = self.pool.begin().await.map_err(internal_error)?;
+
+        let link = query_as!(
+            ShareLink,
+            "insert into share_links (token, user_id, folder, expires_at, password_hash, allow_download, created_at)
+            values ($1, $2, $3, $4, $5, $6, $7) returning *",
+            link.token,
+            link.user_id,
+            link.folder,
+            link.expires_at,
+            link.password_hash,
+            link.allow_download,
+            link.created_at
+        )
+        .fetch_one(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+
+        for photo_id in photo_ids {
+            query!(
+                "insert or ignore into share_link_photos (link_id, photo_id) values ($1, $2)",
+                link.id,
+                photo_id
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+        }
+
+        tx.commit().await.map_err(internal_error)?;
+        Ok(link)
+    }
+
+    pub async fn delete_link(&self, id: i64) -> Result<(), ErrorResponse> {
+        query!("delete from share_links where id = $1", id)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(internal_error)
+    }
+}
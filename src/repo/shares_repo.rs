@@ -0,0 +1,172 @@
+use crate::model::photo_share::{PhotoShare, SharePermission};
+use crate::utils::internal_error;
+use axum::response::ErrorResponse;
+use sqlx::{query, query_as, query_scalar, SqlitePool};
+use time::OffsetDateTime;
+
+#[derive(Clone)]
+pub struct SharesRepository {
+    pool: SqlitePool,
+}
+
+impl SharesRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get_share(&self, id: i64) -> Result<Option<PhotoShare>, ErrorResponse> {
+        query_as!(
+            PhotoShare,
+            r#"select id, owner_id, user_id, photo_id, folder, permission as "permission: SharePermission", created_at
+            from photo_shares where id = $1"#,
+            id
+        )
+        .fetch_optional(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Returns the shares created by the user
+    pub async fn get_shares_by_owner(
+        &self,
+        owner_id: impl AsRef<str>,
+    ) -> Result<Vec<PhotoShare>, ErrorResponse> {
+        let owner_id = owner_id.as_ref();
+        query_as!(
+            PhotoShare,
+            r#"select id, owner_id, user_id, photo_id, folder, permission as "permission: SharePermission", created_at
+            from photo_shares where owner_id = $1 order by id"#,
+            owner_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Returns the shares other users created for this user
+    pub async fn get_shares_with_user(
+        &self,
+        user_id: impl AsRef<str>,
+    ) -> Result<Vec<PhotoShare>, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        query_as!(
+            PhotoShare,
+            r#"select id, owner_id, user_id, photo_id, folder, permission as "permission: SharePermission", created_at
+            from photo_shares where user_id = $1 order by id"#,
+            user_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Returns the highest permission the user was granted for the photo, if any
+    pub async fn get_photo_permission(
+        &self,
+        user_id: impl AsRef<str>,
+        photo_id: i64,
+    ) -> Result<Option<SharePermission>, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        query_scalar!(
+            r#"select permission as "permission!: SharePermission" from shared_photos where user_id = $1 and photo_id = $2"#,
+            user_id,
+            photo_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map(|permissions| permissions.into_iter().max())
+        .map_err(internal_error)
+    }
+
+    ///
+    /// Returns the highest permission the user was granted for the folder of the owner, if any.
+    /// Sharing a folder also shares its subfolders.
+    ///
+    pub async fn get_folder_permission(
+        &self,
+        user_id: impl AsRef<str>,
+        owner_id: impl AsRef<str>,
+        folder: impl AsRef<str>,
+    ) -> Result<Option<SharePermission>, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        let owner_id = owner_id.as_ref();
+        let folder = folder.as_ref();
+        query_scalar!(
+            r#"select permission as "permission: SharePermission" from photo_shares
+            where user_id = $1 and owner_id = $2
+            and ($3 = folder or substr($3, 1, length(folder) + 1) = folder || '/')"#,
+            user_id,
+            owner_id,
+            folder
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map(|permissions| permissions.into_iter().max())
+        .map_err(internal_error)
+    }
+
+    /// Shares a photo, updating the permission if it was already shared with the user
+    pub async fn share_photo(
+        &self,
+        owner_id: impl AsRef<str>,
+        user_id: impl AsRef<str>,
+        photo_id: i64,
+        permission: SharePermission,
+    ) -> Result<PhotoShare, ErrorResponse> {
+        let owner_id = owner_id.as_ref();
+        let user_id = user_id.as_ref();
+        let created_at = OffsetDateTime::now_utc();
+        query_as!(
+            PhotoShare,
+            r#"insert into photo_shares (owner_id, user_id, photo_id, permission, created_at)
+            values ($1, $2, $3, $4, $5)
+            on conflict (user_id, photo_id) where photo_id is not null do update set permission = excluded.permission
+            returning id, owner_id, user_id, photo_id, folder, permission as "permission: SharePermission", created_at"#,
+            owner_id,
+            user_id,
+            photo_id,
+            permission,
+            created_at
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Shares a folder, updating the permission if it was already shared with the user
+    pub async fn share_folder(
+        &self,
+        owner_id: impl AsRef<str>,
+        user_id: impl AsRef<str>,
+        folder: impl AsRef<str>,
+        permission: SharePermission,
+    ) -> Result<PhotoShare, ErrorResponse> {
+        let owner_id = owner_id.as_ref();
+        let user_id = user_id.as_ref();
+        let folder = folder.as_ref();
+        let created_at = OffsetDateTime::now_utc();
+        query_as!(
+            PhotoShare,
+            r#"insert into photo_shares (owner_id, user_id, folder, permission, created_at)
+            values ($1, $2, $3, $4, $5)
+            on conflict (user_id, owner_id, folder) where folder is not null do update set permission = excluded.permission
+            returning id, owner_id, user_id, photo_id, folder, permission as "permission: SharePermission", created_at"#,
+            owner_id,
+            user_id,
+            folder,
+            permission,
+            created_at
+        )
+        .fetch_one(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    pub async fn delete_share(&self, id: i64) -> Result<(), ErrorResponse> {
+        query!("delete from photo_shares where id = $1", id)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(internal_error)
+    }
+}
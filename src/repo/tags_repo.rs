@@ -0,0 +1,103 @@
+use crate::model::tag::TagCount;
+use crate::model::user::PUBLIC_USER_ID;
+use crate::utils::internal_error;
+use axum::response::ErrorResponse;
+use sqlx::{query, query_as, query_scalar, SqlitePool};
+
+#[derive(Clone)]
+pub struct TagsRepository {
+    pool: SqlitePool,
+}
+
+impl TagsRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    /// Returns the tags of the photos visible to the user, the most used first
+    pub async fn get_tags(&self, user_id: impl AsRef<str>) -> Result<Vec<TagCount>, ErrorResponse> {
+        let user_id = user_id.as_ref();
+        query_as!(
+            TagCount,
+            r#"select tags.name as "name!: String", count(*) as "count!: i64" from tags
+            join photo_tags on photo_tags.tag_id = tags.id
+            join photos on photos.id = photo_tags.photo_id
+            where photos.trashed_at is null
+            and (photos.user_id in ($1, $2) or photos.id in (select photo_id from shared_photos where user_id = $1))
+            group by tags.id order by count(*) desc, tags.name"#,
+            user_id,
+            PUBLIC_USER_ID
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    pub async fn get_photo_tags(&self, photo_id: i64) -> Result<Vec<String>, ErrorResponse> {
+        query_scalar!(
+            "select tags.name from tags join photo_tags on photo_tags.tag_id = tags.id
+            where photo_tags.photo_id = $1 order by tags.name",
+            photo_id
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    /// Adds the tags to the photo, creating the ones that don't exist yet
+    pub async fn add_photo_tags(
+        &self,
+        photo_id: i64,
+        tags: &[String],
+    ) -> Result<(), ErrorResponse> {
+        let mut tx = self.pool.begin().await.map_err(internal_error)?;
+
+        for tag in tags {
+            query!(
+                "insert into tags (name) values ($1) on conflict (name) do nothing",
+                tag
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+            query!(
+                "insert or ignore into photo_tags (photo_id, tag_id) select $1, id from tags where name = $2",
+                photo_id,
+                tag
+            )
+            .execute(&mut *tx)
+            .await
+            .map_err(internal_error)?;
+        }
+
+        tx.commit().await.map_err(internal_error)
+    }
+
+    /// Removes the tag from the photo, the tag itself is deleted once no photo has it
+    pub async fn remove_photo_tag(
+        &self,
+        photo_id: i64,
+        tag: impl AsRef<str>,
+    ) -> Result<(), ErrorResponse> {
+        let tag = tag.as_ref();
+        let mut tx = self.pool.begin().await.map_err(internal_error)?;
+
+        query!(
+            "delete from photo_tags where photo_id = $1 and tag_id = (select id from tags where name = $2)",
+            photo_id,
+            tag
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+        query!(
+            "delete from tags where name = $1 and id not in (select tag_id from photo_tags)",
+            tag
+        )
+        .execute(&mut *tx)
+        .await
+        .map_err(internal_error)?;
+
+        tx.commit().await.map_err(internal_error)
+    }
+}
@@ -0,0 +1,88 @@
+use crate::model::upload::Upload;
+use crate::utils::internal_error;
+use axum::response::ErrorResponse;
+use sqlx::{query, query_as, SqlitePool};
+use time::OffsetDateTime;
+
+#[derive(Clone)]
+pub struct UploadsRepository {
+    pool: SqlitePool,
+}
+
+impl UploadsRepository {
+    pub fn new(pool: SqlitePool) -> Self {
+        Self { pool }
+    }
+
+    pub async fn get_upload(&self, id: impl AsRef<str>) -> Result<Option<Upload>, ErrorResponse> {
+        let id = id.as_ref();
+        query_as!(Upload, "select * from uploads where id = $1", id)
+            .fetch_optional(&self.pool)
+            .await
+            .map_err(internal_error)
+    }
+
+    /// Uploads last appended to before the given time, which are considered abandoned
+    pub async fn get_uploads_updated_before(
+        &self,
+        before: OffsetDateTime,
+    ) -> Result<Vec<Upload>, ErrorResponse> {
+        query_as!(
+            Upload,
+            "select * from uploads where updated_at < $1",
+            before
+        )
+        .fetch_all(&self.pool)
+        .await
+        .map_err(internal_error)
+    }
+
+    pub async fn insert_upload(&self, upload: &Upload) -> Result<(), ErrorResponse> {
+        query!(
+            "insert into uploads (id, user_id, name, folder, time_created, make_public, upload_length, upload_offset, started_at, updated_at)
+            values ($1, $2, $3, $4, $5, $6, $7, $8, $9, $10)",
+            upload.id,
+            upload.user_id,
+            upload.name,
+            upload.folder,
+            upload.time_created,
+            upload.make_public,
+            upload.upload_length,
+            upload.upload_offset,
+            upload.started_at,
+            upload.updated_at
+        )
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+        .map_err(internal_error)
+    }
+
+    pub async fn update_offset(
+        &self,
+        id: impl AsRef<str>,
+        upload_offset: i64,
+    ) -> Result<(), ErrorResponse> {
+        let id = id.as_ref();
+        let updated_at = OffsetDateTime::now_utc();
+        query!(
+            "update uploads set upload_offset = $2, updated_at = $3 where id = $1",
+            id,
+            upload_offset,
+            updated_at
+        )
+        .execute(&self.pool)
+        .await
+        .map(|_| ())
+        .map_err(internal_error)
+    }
+
+    pub async fn delete_upload(&self, id: impl AsRef<str>) -> Result<(), ErrorResponse> {
+        let id = id.as_ref();
+        query!("delete from uploads where id = $1", id)
+            .execute(&self.pool)
+            .await
+            .map(|_| ())
+            .map_err(internal_error)
+    }
+}
@@ -0,0 +1,140 @@
+use std::time::Duration;
+
+use axum::response::ErrorResponse;
+use time::OffsetDateTime;
+use tokio::task::JoinHandle;
+use tokio::{fs, task};
+use tracing::{error, info};
+
+use crate::http::AppState;
+use crate::model::photo::{Photo, PhotoBase};
+use crate::utils::internal_error;
+
+const PURGE_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+///
+/// Moves the files of the photos into the trash and marks them as trashed in a single transaction.
+/// Returns the result for each of the photos.
+///
+pub async fn trash_photos(
+    app_state: &AppState,
+    photos: Vec<Photo>,
+) -> Result<Vec<(i64, Result<(), String>)>, ErrorResponse> {
+    let storage = &app_state.storage;
+    let mut results = Vec::with_capacity(photos.len());
+    let mut trashed_photos = Vec::with_capacity(photos.len());
+
+    for photo in photos {
+        match storage.move_to_trash(photo.partial_path(), photo.partial_trash_path()) {
+            Ok(_) => trashed_photos.push(photo),
+            Err(e) => results.push((
+                photo.id,
+                Err(format!("Failed moving the photo to the trash: {e}")),
+            )),
+        }
+    }
+
+    let trashed_ids = trashed_photos
+        .iter()
+        .map(|photo| photo.id)
+        .collect::<Vec<_>>();
+
+    if let Err(e) = app_state
+        .photos_repo
+        .trash_photos(&trashed_ids, OffsetDateTime::now_utc())
+        .await
+    {
+        // Put the files back where the database expects them
+        for photo in &trashed_photos {
+            let _ = storage.restore_from_trash(photo.partial_trash_path(), photo.partial_path());
+        }
+        return Err(e);
+    }
+
+    results.extend(trashed_ids.into_iter().map(|photo_id| (photo_id, Ok(()))));
+    Ok(results)
+}
+
+/// Puts the photo back where it was trashed from, under a new name if that one was taken since
+pub async fn restore_photo(app_state: &AppState, photo: Photo) -> Result<Photo, ErrorResponse> {
+    let storage = &app_state.storage;
+    let restored_photo = Photo {
+        name: storage.available_name(photo.partial_path()),
+        trashed_at: None,
+        ..photo.clone()
+    };
+
+    storage
+        .restore_from_trash(photo.partial_trash_path(), restored_photo.partial_path())
+        .map_err(internal_error)?;
+
+    if let Err(e) = app_state.photos_repo.update_photo(&restored_photo).await {
+        let _ = storage.move_to_trash(restored_photo.partial_path(), photo.partial_trash_path());
+        return Err(e);
+    }
+
+    let _ = fs::remove_dir_all(storage.resolve_trash(photo.partial_trash_folder())).await;
+
+    Ok(restored_photo)
+}
+
+/// Deletes the trashed photos along with their files and previews
+pub async fn delete_permanently(
+    app_state: &AppState,
+    photos: &[Photo],
+) -> Result<(), ErrorResponse> {
+    let storage = &app_state.storage;
+    let photo_ids = photos.iter().map(|photo| photo.id).collect::<Vec<_>>();
+
+    app_state
+        .photos_repo
+        .delete_photos(&photo_ids)
+        .await
+        .map_err(internal_error)?;
+
+    for photo in photos {
+        let _ = fs::remove_dir_all(storage.resolve_trash(photo.partial_trash_folder())).await;
+        let _ = fs::remove_file(storage.resolve_preview(photo.partial_preview_path())).await;
+    }
+
+    Ok(())
+}
+
+///
+/// Periodically deletes the photos that have been in the trash for longer than the retention
+/// period, except for those marked as favorite by someone
+///
+pub fn start_purge_task(app_state: AppState, retention_days: u32) -> JoinHandle<()> {
+    let retention = Duration::from_secs(u64::from(retention_days) * 24 * 60 * 60);
+
+    task::spawn(async move {
+        let mut interval = tokio::time::interval(PURGE_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let trashed_before = OffsetDateTime::now_utc() - retention;
+            let expired = match app_state
+                .photos_repo
+                .get_expired_trash(trashed_before)
+                .await
+            {
+                Ok(expired) => expired,
+                Err(_) => {
+                    error!("Failed to load the expired photos from the trash");
+                    continue;
+                }
+            };
+
+            if expired.is_empty() {
+                continue;
+            }
+
+            info!("Purging {} photos from the trash", expired.len());
+
+            if delete_permanently(&app_state, &expired).await.is_err() {
+                error!("Failed purging the trash");
+            }
+        }
+    })
+}
@@ -0,0 +1,55 @@
+use std::time::Duration;
+
+use time::OffsetDateTime;
+use tokio::task::JoinHandle;
+use tokio::{fs, task};
+use tracing::{error, info};
+
+use crate::http::AppState;
+
+const EXPIRY_INTERVAL: Duration = Duration::from_secs(6 * 60 * 60);
+
+/// Resumable uploads that are not appended to for this long are deleted
+const UPLOAD_EXPIRATION: Duration = Duration::from_secs(7 * 24 * 60 * 60);
+
+/// Periodically deletes the uploads that were abandoned along with their staged file
+pub fn start_expiry_task(app_state: AppState) -> JoinHandle<()> {
+    task::spawn(async move {
+        let mut interval = tokio::time::interval(EXPIRY_INTERVAL);
+
+        loop {
+            interval.tick().await;
+
+            let updated_before = OffsetDateTime::now_utc() - UPLOAD_EXPIRATION;
+            let expired = match app_state
+                .uploads_repo
+                .get_uploads_updated_before(updated_before)
+                .await
+            {
+                Ok(expired) => expired,
+                Err(_) => {
+                    error!("Failed to load the expired uploads");
+                    continue;
+                }
+            };
+
+            if expired.is_empty() {
+                continue;
+            }
+
+            info!("Deleting {} abandoned uploads", expired.len());
+
+            for upload in expired {
+                let _ = fs::remove_file(app_state.storage.resolve_upload(&upload.id)).await;
+                if app_state
+                    .uploads_repo
+                    .delete_upload(&upload.id)
+                    .await
+                    .is_err()
+                {
+                    error!("Failed deleting upload {}", upload.id);
+                }
+            }
+        }
+    })
+}
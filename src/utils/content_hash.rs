@@ -0,0 +1,32 @@
+use std::fs;
+use std::io;
+use std::path::Path;
+
+use sha2::Digest;
+
+pub use sha2::Sha256 as ContentHasher;
+
+/// Hex encodes the hash of a file's content
+pub fn finalize_hash(hasher: ContentHasher) -> String {
+    format!("{:x}", hasher.finalize())
+}
+
+/// Computes the hex encoded SHA-256 of the file's content
+pub fn hash_file<P: AsRef<Path>>(path: P) -> io::Result<String> {
+    let mut file = fs::File::open(path)?;
+    let mut hasher = ContentHasher::new();
+    io::copy(&mut file, &mut hasher)?;
+
+    Ok(finalize_hash(hasher))
+}
+
+/// Normalizes a hash sent by a client, `None` if it is not a hex encoded SHA-256
+pub fn parse_content_hash<S: AsRef<str>>(hash: S) -> Option<String> {
+    let hash = hash.as_ref().trim();
+
+    if hash.len() == 64 && hash.chars().all(|c| c.is_ascii_hexdigit()) {
+        Some(hash.to_ascii_lowercase())
+    } else {
+        None
+    }
+}
@@ -17,6 +17,8 @@ pub struct EnvVariables {
     pub database_url: String,
     pub previews_path: PathBuf,
     pub scan_new_files: bool,
+    pub trash_retention_days: u32,
+    pub geonames_path: Option<PathBuf>,
 }
 
 impl EnvVariables {
@@ -52,6 +54,8 @@ impl EnvVariables {
             database_url: database_url.to_string_lossy().to_string(),
             previews_path,
             scan_new_files: optional_env_var("SCAN_NEW_FILES", true),
+            trash_retention_days: optional_env_var("TRASH_RETENTION_DAYS", 30),
+            geonames_path: std::env::var("GEONAMES_PATH").ok().map(PathBuf::from),
         }
     }
 }
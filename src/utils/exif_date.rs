@@ -0,0 +1,247 @@
+use std::fs::OpenOptions;
+use std::io::{self, Read, Seek, SeekFrom, Write};
+use std::path::Path;
+
+use time::macros::format_description;
+use time::OffsetDateTime;
+
+const TAG_DATE_TIME: u16 = 0x0132;
+const TAG_EXIF_IFD_POINTER: u16 = 0x8769;
+const TAG_DATE_TIME_ORIGINAL: u16 = 0x9003;
+const TAG_DATE_TIME_DIGITIZED: u16 = 0x9004;
+const TYPE_ASCII: u16 = 2;
+/// `YYYY:MM:DD HH:MM:SS` and the NUL terminator
+const DATE_LENGTH: u32 = 20;
+
+///
+/// Overwrites the DateTimeOriginal, DateTimeDigitized and DateTime tags of a JPEG in place,
+/// so the date is found again when the photo is scanned. The tags are not added when missing,
+/// at least one of them must already exist.
+///
+pub fn write_exif_date<P: AsRef<Path>>(path: P, date: OffsetDateTime) -> io::Result<()> {
+    let mut file = OpenOptions::new().read(true).write(true).open(path)?;
+    patch_exif_date(&mut file, date)?;
+    file.sync_all()
+}
+
+/// Makes sure [write_exif_date] can replace the date of the photo, without modifying it
+pub fn check_exif_date<P: AsRef<Path>>(path: P) -> io::Result<()> {
+    let mut file = OpenOptions::new().read(true).open(path)?;
+    find_date_offsets(&mut file).map(|_| ())
+}
+
+/// Returns the position of the TIFF header in the file and the offsets of the dates inside it
+fn find_date_offsets<F: Read + Seek>(file: &mut F) -> io::Result<(u64, Vec<u32>)> {
+    let (tiff_start, tiff) = read_jpeg_exif(file)?;
+    let offsets = date_value_offsets(&tiff).ok_or_else(|| invalid_data("Malformed EXIF"))?;
+    if offsets.is_empty() {
+        return Err(invalid_data("The photo has no EXIF date to replace"));
+    }
+
+    Ok((tiff_start, offsets))
+}
+
+fn patch_exif_date<F: Read + Write + Seek>(file: &mut F, date: OffsetDateTime) -> io::Result<()> {
+    let (tiff_start, offsets) = find_date_offsets(file)?;
+
+    let text = date
+        .format(format_description!(
+            "[year]:[month]:[day] [hour]:[minute]:[second]"
+        ))
+        .map_err(io::Error::other)?;
+    if text.len() != DATE_LENGTH as usize - 1 {
+        return Err(invalid_data("The date can't be written in the EXIF"));
+    }
+
+    for offset in offsets {
+        file.seek(SeekFrom::Start(tiff_start + u64::from(offset)))?;
+        file.write_all(text.as_bytes())?;
+    }
+
+    file.flush()
+}
+
+/// Returns the position of the TIFF header in the file and the content of the EXIF segment
+fn read_jpeg_exif<F: Read + Seek>(file: &mut F) -> io::Result<(u64, Vec<u8>)> {
+    let not_jpeg = || invalid_data("Only the EXIF of JPEG photos can be written");
+
+    let mut marker = [0u8; 2];
+    file.read_exact(&mut marker)?;
+    if marker != [0xFF, 0xD8] {
+        return Err(not_jpeg());
+    }
+
+    loop {
+        file.read_exact(&mut marker)?;
+        if marker[0] != 0xFF {
+            return Err(not_jpeg());
+        }
+        // Fill bytes before the marker
+        while marker[1] == 0xFF {
+            file.read_exact(&mut marker[1..])?;
+        }
+        // Start of the image data or end of the image, there is no EXIF
+        if marker[1] == 0xDA || marker[1] == 0xD9 {
+            return Err(invalid_data("The photo has no EXIF"));
+        }
+
+        let mut length = [0u8; 2];
+        file.read_exact(&mut length)?;
+        let length = u16::from_be_bytes(length);
+        if length < 2 {
+            return Err(not_jpeg());
+        }
+
+        let mut segment = vec![0u8; usize::from(length - 2)];
+        file.read_exact(&mut segment)?;
+
+        if marker[1] == 0xE1 && segment.starts_with(b"Exif\0\0") {
+            let tiff_start = file.stream_position()? - segment.len() as u64 + 6;
+            return Ok((tiff_start, segment.split_off(6)));
+        }
+    }
+}
+
+/// Offsets inside the TIFF of the values of the date tags, `None` if the TIFF is malformed
+fn date_value_offsets(tiff: &[u8]) -> Option<Vec<u32>> {
+    let big_endian = match tiff.get(0..2)? {
+        b"II" => false,
+        b"MM" => true,
+        _ => return None,
+    };
+    let u16_at = |offset: usize| -> Option<u16> {
+        let bytes = tiff.get(offset..offset + 2)?.try_into().ok()?;
+        Some(match big_endian {
+            true => u16::from_be_bytes(bytes),
+            false => u16::from_le_bytes(bytes),
+        })
+    };
+    let u32_at = |offset: usize| -> Option<u32> {
+        let bytes = tiff.get(offset..offset + 4)?.try_into().ok()?;
+        Some(match big_endian {
+            true => u32::from_be_bytes(bytes),
+            false => u32::from_le_bytes(bytes),
+        })
+    };
+    // (tag, value or offset) of the entries of the IFD at the offset
+    let ifd_entries = |offset: usize| -> Option<Vec<(u16, u16, u32, u32)>> {
+        let count = usize::from(u16_at(offset)?);
+        (0..count)
+            .map(|index| {
+                let entry = offset + 2 + index * 12;
+                Some((
+                    u16_at(entry)?,
+                    u16_at(entry + 2)?,
+                    u32_at(entry + 4)?,
+                    u32_at(entry + 8)?,
+                ))
+            })
+            .collect()
+    };
+
+    let ifd0 = ifd_entries(u32_at(4)? as usize)?;
+    let mut entries = ifd0
+        .iter()
+        .filter(|(tag, ..)| *tag == TAG_DATE_TIME)
+        .copied()
+        .collect::<Vec<_>>();
+
+    if let Some((.., exif_offset)) = ifd0.iter().find(|(tag, ..)| *tag == TAG_EXIF_IFD_POINTER) {
+        entries.extend(
+            ifd_entries(*exif_offset as usize)?
+                .into_iter()
+                .filter(|(tag, ..)| {
+                    *tag == TAG_DATE_TIME_ORIGINAL || *tag == TAG_DATE_TIME_DIGITIZED
+                }),
+        );
+    }
+
+    Some(
+        entries
+            .into_iter()
+            .filter(|(_, value_type, count, offset)| {
+                *value_type == TYPE_ASCII
+                    && *count >= DATE_LENGTH
+                    && (*offset as usize).saturating_add(DATE_LENGTH as usize) <= tiff.len()
+            })
+            .map(|(.., offset)| offset)
+            .collect(),
+    )
+}
+
+fn invalid_data(message: &str) -> io::Error {
+    io::Error::new(io::ErrorKind::InvalidData, message.to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use std::io::Cursor;
+
+    use exif::{In, Tag};
+    use time::macros::datetime;
+
+    use super::*;
+
+    /// JPEG with the DateTime tag in IFD0 and DateTimeOriginal in the EXIF IFD
+    fn jpeg_with_dates() -> Vec<u8> {
+        let date = b"2001:01:01 00:00:00\0";
+        let mut tiff = b"II*\0".to_vec();
+        tiff.extend(8u32.to_le_bytes());
+
+        // IFD0 at 8 with 2 entries, then its date value at 38
+        let exif_ifd = 38 + 20;
+        tiff.extend(2u16.to_le_bytes());
+        for (tag, value_type, value) in [
+            (TAG_DATE_TIME, TYPE_ASCII, 38u32),
+            (TAG_EXIF_IFD_POINTER, 4, exif_ifd),
+        ] {
+            tiff.extend(tag.to_le_bytes());
+            tiff.extend(value_type.to_le_bytes());
+            tiff.extend(if value_type == TYPE_ASCII { 20u32 } else { 1 }.to_le_bytes());
+            tiff.extend(value.to_le_bytes());
+        }
+        tiff.extend(0u32.to_le_bytes());
+        tiff.extend(date);
+
+        // EXIF IFD with 1 entry, then its date value
+        tiff.extend(1u16.to_le_bytes());
+        tiff.extend(TAG_DATE_TIME_ORIGINAL.to_le_bytes());
+        tiff.extend(TYPE_ASCII.to_le_bytes());
+        tiff.extend(20u32.to_le_bytes());
+        tiff.extend((exif_ifd + 2 + 12 + 4).to_le_bytes());
+        tiff.extend(0u32.to_le_bytes());
+        tiff.extend(date);
+
+        let mut jpeg = vec![0xFF, 0xD8, 0xFF, 0xE0, 0x00, 0x04, 0x00, 0x00, 0xFF, 0xE1];
+        jpeg.extend((tiff.len() as u16 + 8).to_be_bytes());
+        jpeg.extend(b"Exif\0\0");
+        jpeg.extend(tiff);
+        jpeg.extend([0xFF, 0xD9]);
+        jpeg
+    }
+
+    #[test]
+    fn patches_dates() {
+        let mut file = Cursor::new(jpeg_with_dates());
+        patch_exif_date(&mut file, datetime!(2016-09-22 16:04:30 UTC)).unwrap();
+
+        let exif = exif::Reader::new()
+            .read_from_container(&mut Cursor::new(file.into_inner()))
+            .unwrap();
+        for tag in [Tag::DateTime, Tag::DateTimeOriginal] {
+            let field = exif.get_field(tag, In::PRIMARY).unwrap();
+            assert_eq!(
+                field.display_value().to_string(),
+                "2016-09-22 16:04:30",
+                "{tag}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_other_files() {
+        let date = datetime!(2016-09-22 16:04:30 UTC);
+        assert!(patch_exif_date(&mut Cursor::new(b"\x89PNG".to_vec()), date).is_err());
+        assert!(patch_exif_date(&mut Cursor::new(vec![0xFF, 0xD8, 0xFF, 0xD9]), date).is_err());
+    }
+}
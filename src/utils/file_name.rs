@@ -0,0 +1,118 @@
+use std::path::Path;
+
+/// Longest name most file systems allow, in bytes
+const MAX_NAME_LENGTH: usize = 255;
+
+///
+/// Checks that the name can be used as a single file or folder name in the storage.
+/// Hidden names are rejected as well, as they are reserved for the server's own folders.
+///
+pub fn validate_name(name: &str) -> Result<(), String> {
+    if name.trim().is_empty() {
+        return Err("The name can't be empty".to_string());
+    }
+    if name.len() > MAX_NAME_LENGTH {
+        return Err(format!(
+            "The name can't be longer than {MAX_NAME_LENGTH} bytes"
+        ));
+    }
+    if name != name.trim() {
+        return Err("The name can't start or end with a space".to_string());
+    }
+    if name.starts_with('.') {
+        return Err("The name can't start with a '.'".to_string());
+    }
+    if let Some(c) = name.chars().find(|c| {
+        matches!(c, '/' | '\\' | ':' | '*' | '?' | '"' | '<' | '>' | '|') || c.is_control()
+    }) {
+        return Err(format!("The name can't contain {c:?}"));
+    }
+
+    Ok(())
+}
+
+///
+/// Checks that the folder can be used as a relative path in the storage, with its
+/// subfolders separated by '/', ex: "2019/Holidays/Italy"
+///
+pub fn validate_folder(folder: &str) -> Result<(), String> {
+    if folder.is_empty() {
+        return Err("The folder can't be empty".to_string());
+    }
+
+    folder.split('/').try_for_each(validate_name)
+}
+
+/// The folder relative to the root, with its subfolders separated by '/', `None` for the root itself
+pub fn relative_folder(root: &Path, folder: &Path) -> Option<String> {
+    let folder = folder
+        .strip_prefix(root)
+        .ok()?
+        .components()
+        .map(|component| component.as_os_str().to_string_lossy())
+        .collect::<Vec<_>>()
+        .join("/");
+
+    (!folder.is_empty()).then_some(folder)
+}
+
+/// Lowercase extension of the file name, if it has one
+pub fn extension(name: &str) -> Option<String> {
+    Path::new(name)
+        .extension()
+        .map(|extension| extension.to_string_lossy().to_lowercase())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn name_validation() {
+        assert!(validate_name("IMG_2016 (1).jpg").is_ok());
+        assert!(validate_name("Brașov").is_ok());
+
+        assert!(validate_name("").is_err());
+        assert!(validate_name("   ").is_err());
+        assert!(validate_name(" a.jpg").is_err());
+        assert!(validate_name(".hidden").is_err());
+        assert!(validate_name("..").is_err());
+        assert!(validate_name("a/b.jpg").is_err());
+        assert!(validate_name("a\\b.jpg").is_err());
+        assert!(validate_name("a\0.jpg").is_err());
+        assert!(validate_name(&"a".repeat(256)).is_err());
+    }
+
+    #[test]
+    fn folder_validation() {
+        assert!(validate_folder("Trip").is_ok());
+        assert!(validate_folder("2019/Holidays/Italy").is_ok());
+
+        assert!(validate_folder("").is_err());
+        assert!(validate_folder("/2019").is_err());
+        assert!(validate_folder("2019/").is_err());
+        assert!(validate_folder("2019//Italy").is_err());
+        assert!(validate_folder("2019/../bob").is_err());
+        assert!(validate_folder("2019/.hidden").is_err());
+        assert!(validate_folder("2019\\Italy").is_err());
+    }
+
+    #[test]
+    fn relative_folders() {
+        let root = Path::new("/photos/alice");
+
+        assert_eq!(relative_folder(root, root), None);
+        assert_eq!(
+            relative_folder(root, Path::new("/photos/alice/2019/Holidays/Italy")),
+            Some("2019/Holidays/Italy".to_string())
+        );
+        assert_eq!(relative_folder(root, Path::new("/photos/bob/Trip")), None);
+    }
+
+    #[test]
+    fn extensions() {
+        assert_eq!(extension("a.JPG"), Some("jpg".to_string()));
+        assert_eq!(extension("archive.tar.gz"), Some("gz".to_string()));
+        assert_eq!(extension("README"), None);
+    }
+}
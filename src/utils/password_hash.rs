@@ -18,10 +18,10 @@ pub fn generate_random_password() -> String {
 pub fn generate_hash_from_password<T: AsRef<str>>(password: T) -> String {
     let salt = SaltString::generate(&mut rand::thread_rng());
 
-    return Argon2::default()
+    Argon2::default()
         .hash_password(password.as_ref().as_bytes(), &salt)
         .expect("Failed to hash password")
-        .to_string();
+        .to_string()
 }
 
 pub fn validate_credentials<T: AsRef<str>, E: AsRef<str>>(
@@ -30,7 +30,7 @@ pub fn validate_credentials<T: AsRef<str>, E: AsRef<str>>(
 ) -> Result<bool, password_hash::Error> {
     let expected_password_hash = PasswordHash::new(expected_password_hash.as_ref())?;
 
-    return Ok(Argon2::default()
+    Ok(Argon2::default()
         .verify_password(password.as_ref().as_bytes(), &expected_password_hash)
-        .is_ok());
+        .is_ok())
 }
@@ -0,0 +1,155 @@
+use std::fs;
+use std::io::BufReader;
+use std::path::Path;
+use std::str::from_utf8;
+
+use exif::{Exif, Field, In, Rational, Tag, Value};
+use mime_guess::MimeGuess;
+use serde::Serialize;
+
+use crate::model::photo_metadata::PhotoMetadata;
+
+/// Parses the EXIF of an image, returns `None` for other files or if it has none
+pub fn read_exif<P: AsRef<Path>>(path: P) -> Option<Exif> {
+    let path = path.as_ref();
+    let mime = MimeGuess::from_ext(path.extension()?.to_str()?).first_or_octet_stream();
+    if mime.type_() != "image" {
+        return None;
+    }
+
+    let file = fs::File::open(path).ok()?;
+    let mut bufreader = BufReader::new(&file);
+    exif::Reader::new().read_from_container(&mut bufreader).ok()
+}
+
+#[derive(Debug, Serialize)]
+pub struct ExifField {
+    tag: String,
+    value: String,
+}
+
+/// Lists every EXIF field of the image as text
+pub fn read_exif_fields<P: AsRef<Path>>(path: P) -> Option<Vec<ExifField>> {
+    let exif = read_exif(path)?;
+
+    Some(
+        exif.fields()
+            .filter(|field| field.ifd_num == In::PRIMARY)
+            .map(|field| ExifField {
+                tag: field.tag.to_string(),
+                value: field.value.display_as(field.tag).to_string(),
+            })
+            .collect(),
+    )
+}
+
+/// Reads the metadata of the file, which is empty if it has no EXIF
+pub fn read_metadata<P: AsRef<Path>>(path: P) -> PhotoMetadata {
+    read_exif(path)
+        .map(|exif| extract_metadata(&exif))
+        .unwrap_or_default()
+}
+
+pub fn extract_metadata(exif: &Exif) -> PhotoMetadata {
+    let field = |tag: Tag| exif.get_field(tag, In::PRIMARY);
+    let text = |tag: Tag| field(tag).and_then(ascii);
+    let number = |tag: Tag| field(tag).and_then(rational);
+    let integer = |tag: Tag| {
+        field(tag)
+            .and_then(|field| field.value.get_uint(0))
+            .map(i64::from)
+    };
+
+    let latitude = field(Tag::GPSLatitude).and_then(degrees).map(|latitude| {
+        match text(Tag::GPSLatitudeRef).as_deref() {
+            Some("S") => -latitude,
+            _ => latitude,
+        }
+    });
+    let longitude = field(Tag::GPSLongitude).and_then(degrees).map(|longitude| {
+        match text(Tag::GPSLongitudeRef).as_deref() {
+            Some("W") => -longitude,
+            _ => longitude,
+        }
+    });
+    let altitude = number(Tag::GPSAltitude).map(|altitude| match integer(Tag::GPSAltitudeRef) {
+        // Below the sea level
+        Some(1) => -altitude,
+        _ => altitude,
+    });
+
+    PhotoMetadata {
+        camera_make: text(Tag::Make),
+        camera_model: text(Tag::Model),
+        lens_model: text(Tag::LensModel),
+        exposure_time: number(Tag::ExposureTime),
+        f_number: number(Tag::FNumber),
+        iso: integer(Tag::PhotographicSensitivity),
+        focal_length: number(Tag::FocalLength),
+        width: integer(Tag::PixelXDimension).or_else(|| integer(Tag::ImageWidth)),
+        height: integer(Tag::PixelYDimension).or_else(|| integer(Tag::ImageLength)),
+        orientation: integer(Tag::Orientation),
+        latitude,
+        longitude,
+        altitude,
+        // Assigned by the reverse geocoder
+        ..Default::default()
+    }
+}
+
+fn ascii(field: &Field) -> Option<String> {
+    match &field.value {
+        Value::Ascii(values) => values
+            .iter()
+            .filter_map(|value| from_utf8(value).ok())
+            .map(|value| value.trim_matches(|c: char| c == '\0' || c.is_whitespace()))
+            .find(|value| !value.is_empty())
+            .map(String::from),
+        _ => None,
+    }
+}
+
+fn rational(field: &Field) -> Option<f64> {
+    match &field.value {
+        Value::Rational(values) => values.first().map(Rational::to_f64),
+        Value::SRational(values) => values.first().map(|value| value.to_f64()),
+        _ => None,
+    }
+    .filter(|value| value.is_finite())
+}
+
+/// Converts the GPS degrees, minutes and seconds into decimal degrees
+fn degrees(field: &Field) -> Option<f64> {
+    match &field.value {
+        Value::Rational(values) => dms_to_degrees(values),
+        _ => None,
+    }
+}
+
+fn dms_to_degrees(values: &[Rational]) -> Option<f64> {
+    let degrees = values.first()?.to_f64();
+    let minutes = values.get(1).map_or(0.0, Rational::to_f64);
+    let seconds = values.get(2).map_or(0.0, Rational::to_f64);
+
+    Some(degrees + minutes / 60.0 + seconds / 3600.0).filter(|degrees| degrees.is_finite())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn gps_degrees() {
+        let values = [
+            Rational::from((44, 1)),
+            Rational::from((25, 1)),
+            Rational::from((3150, 100)),
+        ];
+        let degrees = dms_to_degrees(&values).unwrap();
+        assert!((degrees - 44.42541666).abs() < 1e-6);
+
+        assert_eq!(dms_to_degrees(&[Rational::from((10, 1))]), Some(10.0));
+        assert_eq!(dms_to_degrees(&[Rational::from((10, 0))]), None);
+        assert_eq!(dms_to_degrees(&[]), None);
+    }
+}
@@ -1,10 +1,15 @@
+use std::collections::HashSet;
 use std::fs;
 use std::path::{Path, PathBuf};
 
+use crate::utils::xmp;
+
 #[derive(Clone)]
 pub struct StorageResolver {
     storage_folder: PathBuf,
     preview_folder: PathBuf,
+    uploads_folder: PathBuf,
+    trash_folder: PathBuf,
 }
 
 impl StorageResolver {
@@ -27,9 +32,25 @@ impl StorageResolver {
             });
         }
 
+        // Staging area for resumable uploads, kept on the same file system as the photos
+        // so that finished uploads can be moved into place
+        let uploads_folder = storage_folder.join(".uploads");
+        if !uploads_folder.exists() {
+            fs::create_dir_all(&uploads_folder).unwrap_or_else(|_| {
+                panic!(
+                    "Failed to create uploads folder at {}",
+                    uploads_folder.display()
+                )
+            });
+        }
+
+        let trash_folder = storage_folder.join(".trash");
+
         StorageResolver {
             storage_folder,
             preview_folder,
+            uploads_folder,
+            trash_folder,
         }
     }
 
@@ -41,6 +62,53 @@ impl StorageResolver {
         self.preview_folder.join(relative.as_ref())
     }
 
+    pub fn resolve_upload<P: AsRef<Path>>(&self, upload_id: P) -> PathBuf {
+        self.uploads_folder.join(upload_id.as_ref())
+    }
+
+    pub fn resolve_trash<P: AsRef<Path>>(&self, relative: P) -> PathBuf {
+        self.trash_folder.join(relative.as_ref())
+    }
+
+    ///
+    /// Returns the file name of the given photo path if it is free, otherwise the first free
+    /// name in the same folder in the format "name (1).ext"
+    ///
+    pub fn available_name<P: AsRef<Path>>(&self, relative: P) -> String {
+        self.available_name_excluding(relative, &HashSet::new())
+    }
+
+    /// Same as [Self::available_name], also treating the given names as taken even though
+    /// they have no file, ex: the names of trashed photos
+    pub fn available_name_excluding<P: AsRef<Path>>(
+        &self,
+        relative: P,
+        taken_names: &HashSet<String>,
+    ) -> String {
+        let relative = relative.as_ref();
+        let name = relative.file_name().unwrap_or_default().to_string_lossy();
+        let stem = relative.file_stem().unwrap_or_default().to_string_lossy();
+        let extension = relative
+            .extension()
+            .map(|extension| extension.to_string_lossy());
+
+        let mut candidate = name.to_string();
+        let mut copy_index = 0;
+        while taken_names.contains(&candidate)
+            || self
+                .resolve_photo(relative.with_file_name(&candidate))
+                .exists()
+        {
+            copy_index += 1;
+            candidate = match &extension {
+                Some(extension) => format!("{stem} ({copy_index}).{extension}"),
+                None => format!("{stem} ({copy_index})"),
+            };
+        }
+
+        candidate
+    }
+
     pub fn move_photo<P1: AsRef<Path>, P2: AsRef<Path>>(
         &self,
         src_relative: P1,
@@ -49,12 +117,122 @@ impl StorageResolver {
         let destination_path = self.resolve_photo(dest_relative);
 
         // Create parent directory if it doesn't exist
-        if let Some(parent) = destination_path.parent() {
-            if !parent.exists() {
-                fs::create_dir_all(parent)?;
-            }
+        if let Some(parent) = destination_path.parent()
+            && !parent.exists()
+        {
+            fs::create_dir_all(parent)?;
+        }
+
+        rename_with_sidecars(&self.resolve_photo(src_relative), &destination_path)
+    }
+
+    /// Renames the photo inside its folder, failing if the new name is already taken
+    pub fn rename_photo<P1: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        src_relative: P1,
+        dest_relative: P2,
+    ) -> std::io::Result<()> {
+        let destination_path = self.resolve_photo(dest_relative);
+
+        if destination_path.exists() {
+            return Err(std::io::Error::from(std::io::ErrorKind::AlreadyExists));
+        }
+
+        rename_with_sidecars(&self.resolve_photo(src_relative), &destination_path)
+    }
+
+    /// Moves a whole folder of photos with its subfolders, failing if the destination already exists
+    pub fn move_folder<P1: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        src_relative: P1,
+        dest_relative: P2,
+    ) -> std::io::Result<()> {
+        let destination_path = self.resolve_photo(dest_relative);
+
+        if destination_path.exists() {
+            return Err(std::io::Error::from(std::io::ErrorKind::AlreadyExists));
+        }
+
+        if let Some(parent) = destination_path.parent()
+            && !parent.exists()
+        {
+            fs::create_dir_all(parent)?;
         }
 
         fs::rename(self.resolve_photo(src_relative), destination_path)
     }
+
+    pub fn move_to_trash<P1: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        photo_relative: P1,
+        trash_relative: P2,
+    ) -> std::io::Result<()> {
+        let trash_path = self.resolve_trash(trash_relative);
+
+        if let Some(parent) = trash_path.parent()
+            && !parent.exists()
+        {
+            fs::create_dir_all(parent)?;
+        }
+
+        rename_with_sidecars(&self.resolve_photo(photo_relative), &trash_path)
+    }
+
+    /// Moves a trashed photo to the path it will be restored from
+    pub fn move_in_trash<P1: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        src_relative: P1,
+        dest_relative: P2,
+    ) -> std::io::Result<()> {
+        let destination_path = self.resolve_trash(dest_relative);
+
+        if let Some(parent) = destination_path.parent()
+            && !parent.exists()
+        {
+            fs::create_dir_all(parent)?;
+        }
+
+        rename_with_sidecars(&self.resolve_trash(src_relative), &destination_path)
+    }
+
+    pub fn restore_from_trash<P1: AsRef<Path>, P2: AsRef<Path>>(
+        &self,
+        trash_relative: P1,
+        photo_relative: P2,
+    ) -> std::io::Result<()> {
+        let photo_path = self.resolve_photo(photo_relative);
+
+        if let Some(parent) = photo_path.parent()
+            && !parent.exists()
+        {
+            fs::create_dir_all(parent)?;
+        }
+
+        rename_with_sidecars(&self.resolve_trash(trash_relative), &photo_path)
+    }
+}
+
+///
+/// Renames the file of a photo along with its sidecars: the Google Takeout metadata "IMG_1.jpg.json"
+/// and the XMP sidecars. A sidecar is left behind if its new name is already taken.
+///
+fn rename_with_sidecars(source: &Path, destination: &Path) -> std::io::Result<()> {
+    fs::rename(source, destination)?;
+
+    for (source_sidecar, destination_sidecar) in
+        sidecar_paths(source).zip(sidecar_paths(destination))
+    {
+        if source_sidecar.exists() && !destination_sidecar.exists() {
+            let _ = fs::rename(source_sidecar, destination_sidecar);
+        }
+    }
+
+    Ok(())
+}
+
+fn sidecar_paths(path: &Path) -> impl Iterator<Item = PathBuf> {
+    let mut json_path = path.as_os_str().to_owned();
+    json_path.push(".json");
+
+    std::iter::once(PathBuf::from(json_path)).chain(xmp::sidecar_paths(path))
 }
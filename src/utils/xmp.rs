@@ -0,0 +1,107 @@
+use std::fs;
+use std::io::Read;
+use std::path::{Path, PathBuf};
+use std::sync::LazyLock;
+
+use regex::Regex;
+
+/// XMP packets are written near the start of the files, so only this much of them is searched
+const XMP_SEARCH_LENGTH: u64 = 1024 * 1024;
+
+const XMP_START: &[u8] = b"<x:xmpmeta";
+const XMP_END: &[u8] = b"</x:xmpmeta>";
+
+///
+/// Reads the `xmp:Rating` of the photo from its XMP sidecar, "IMG_1.xmp" or "IMG_1.jpg.xmp",
+/// or otherwise from the XMP packet embedded in the file.
+/// Unrated and rejected photos have no rating.
+///
+pub fn read_rating<P: AsRef<Path>>(path: P) -> Option<i64> {
+    let path = path.as_ref();
+
+    sidecar_paths(path)
+        .into_iter()
+        .find_map(|sidecar| fs::read_to_string(sidecar).ok())
+        .or_else(|| read_embedded_packet(path))
+        .and_then(|xmp| parse_rating(&xmp))
+}
+
+/// Whether the file is an XMP sidecar instead of a photo
+pub fn is_sidecar(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension.eq_ignore_ascii_case("xmp"))
+}
+
+/// The XMP sidecars a photo can have, "IMG_1.xmp" and "IMG_1.jpg.xmp"
+pub fn sidecar_paths(path: &Path) -> [PathBuf; 2] {
+    let mut full_name = path.as_os_str().to_owned();
+    full_name.push(".xmp");
+
+    [path.with_extension("xmp"), PathBuf::from(full_name)]
+}
+
+fn read_embedded_packet(path: &Path) -> Option<String> {
+    let mut bytes = Vec::new();
+    fs::File::open(path)
+        .ok()?
+        .take(XMP_SEARCH_LENGTH)
+        .read_to_end(&mut bytes)
+        .ok()?;
+
+    let start = find(&bytes, XMP_START)?;
+    let end = start + find(&bytes[start..], XMP_END)? + XMP_END.len();
+
+    Some(String::from_utf8_lossy(&bytes[start..end]).into_owned())
+}
+
+fn find(haystack: &[u8], needle: &[u8]) -> Option<usize> {
+    haystack
+        .windows(needle.len())
+        .position(|window| window == needle)
+}
+
+/// The rating is either an attribute or an element of the `rdf:Description`
+fn parse_rating(xmp: &str) -> Option<i64> {
+    static RATING_PATTERN: LazyLock<Regex> = LazyLock::new(|| {
+        Regex::new(r#"xmp:Rating\s*=\s*["'](-?\d+)["']|<xmp:Rating>\s*(-?\d+)\s*</xmp:Rating>"#)
+            .unwrap()
+    });
+
+    let capture = RATING_PATTERN.captures(xmp)?;
+    let rating = capture.get(1).or(capture.get(2))?.as_str().parse().ok()?;
+
+    (1..=5).contains(&rating).then_some(rating)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rating_parsing() {
+        let attribute = r#"<x:xmpmeta xmlns:x="adobe:ns:meta/"><rdf:RDF>
+            <rdf:Description rdf:about="" xmp:Rating="4" xmp:Label="Red"/>
+            </rdf:RDF></x:xmpmeta>"#;
+        assert_eq!(parse_rating(attribute), Some(4));
+
+        let element = "<rdf:Description><xmp:Rating>2</xmp:Rating></rdf:Description>";
+        assert_eq!(parse_rating(element), Some(2));
+
+        assert_eq!(parse_rating(r#"xmp:Rating="-1""#), None);
+        assert_eq!(parse_rating(r#"xmp:Rating="0""#), None);
+        assert_eq!(parse_rating("<rdf:Description/>"), None);
+    }
+
+    #[test]
+    fn sidecars() {
+        assert!(is_sidecar(Path::new("IMG_1.XMP")));
+        assert!(!is_sidecar(Path::new("IMG_1.jpg")));
+        assert_eq!(
+            sidecar_paths(Path::new("a/IMG_1.CR2")),
+            [
+                PathBuf::from("a/IMG_1.xmp"),
+                PathBuf::from("a/IMG_1.CR2.xmp")
+            ]
+        );
+    }
+}
@@ -0,0 +1,327 @@
+//!
+//! Minimal ZIP writer producing store-only (uncompressed) archives on the fly,
+//! so that they can be streamed without knowing the content of the entries in advance.
+//! The CRC-32 and the sizes of each entry are written in a data descriptor after its content.
+//! As the size of an entry isn't known when its header is written, every entry announces ZIP64
+//! sizes, the central directory only uses the ZIP64 extensions past 4 GiB or 65535 entries.
+//!
+
+use std::io;
+
+use time::OffsetDateTime;
+use tokio::io::{AsyncRead, AsyncReadExt, AsyncWrite, AsyncWriteExt};
+
+const LOCAL_FILE_HEADER_SIGNATURE: u32 = 0x04034b50;
+const DATA_DESCRIPTOR_SIGNATURE: u32 = 0x08074b50;
+const CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x02014b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06064b50;
+const ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE: u32 = 0x07064b50;
+const END_OF_CENTRAL_DIRECTORY_SIGNATURE: u32 = 0x06054b50;
+
+/// Version 4.5 is required for ZIP64
+const VERSION_NEEDED: u16 = 45;
+/// Made by a Unix system, so that the permissions in the external attributes are used
+const VERSION_MADE_BY: u16 = (3 << 8) | VERSION_NEEDED;
+/// Bit 3: the CRC-32 and sizes follow the content, bit 11: the name is UTF-8
+const GENERAL_PURPOSE_FLAGS: u16 = (1 << 3) | (1 << 11);
+/// Regular file with rw-r--r-- permissions
+const EXTERNAL_ATTRIBUTES: u32 = 0o100644 << 16;
+
+const ZIP64_EXTRA_FIELD_ID: u16 = 0x0001;
+const EXTENDED_TIMESTAMP_EXTRA_FIELD_ID: u16 = 0x5455;
+
+const ZIP64_LIMIT: u64 = u32::MAX as u64;
+
+struct CentralDirectoryEntry {
+    name: String,
+    modified: OffsetDateTime,
+    crc32: u32,
+    size: u64,
+    offset: u64,
+}
+
+pub struct ZipWriter<W> {
+    writer: W,
+    /// Amount of bytes written so far
+    offset: u64,
+    entries: Vec<CentralDirectoryEntry>,
+}
+
+impl<W: AsyncWrite + Unpin> ZipWriter<W> {
+    pub fn new(writer: W) -> Self {
+        Self {
+            writer,
+            offset: 0,
+            entries: Vec::new(),
+        }
+    }
+
+    /// Adds an entry with the given path inside the archive, copying the content from the reader
+    pub async fn add_file<R: AsyncRead + Unpin>(
+        &mut self,
+        name: &str,
+        modified: OffsetDateTime,
+        mut reader: R,
+    ) -> io::Result<()> {
+        let offset = self.offset;
+        let (dos_time, dos_date) = to_dos_date_time(modified);
+
+        let mut header = Vec::with_capacity(30 + name.len() + 20 + 9);
+        put_u32(&mut header, LOCAL_FILE_HEADER_SIGNATURE);
+        put_u16(&mut header, VERSION_NEEDED);
+        put_u16(&mut header, GENERAL_PURPOSE_FLAGS);
+        put_u16(&mut header, 0); // Stored
+        put_u16(&mut header, dos_time);
+        put_u16(&mut header, dos_date);
+        // The CRC-32 and the sizes are in the data descriptor, the sizes being in ZIP64 format
+        put_u32(&mut header, 0);
+        put_u32(&mut header, ZIP64_LIMIT as u32);
+        put_u32(&mut header, ZIP64_LIMIT as u32);
+        put_u16(&mut header, name.len() as u16);
+        put_u16(&mut header, 20 + 9);
+        header.extend_from_slice(name.as_bytes());
+        // With this extra field, the data descriptor must have 8 bytes sizes (APPNOTE 4.3.9.2)
+        put_u16(&mut header, ZIP64_EXTRA_FIELD_ID);
+        put_u16(&mut header, 16);
+        put_u64(&mut header, 0);
+        put_u64(&mut header, 0);
+        put_extended_timestamp(&mut header, modified);
+        self.write(&header).await?;
+
+        let mut hasher = crc32fast::Hasher::new();
+        let mut size = 0u64;
+        let mut buffer = vec![0u8; 64 * 1024];
+        loop {
+            let read = reader.read(&mut buffer).await?;
+            if read == 0 {
+                break;
+            }
+
+            hasher.update(&buffer[..read]);
+            self.write(&buffer[..read]).await?;
+            size += read as u64;
+        }
+        let crc32 = hasher.finalize();
+
+        let mut descriptor = Vec::with_capacity(24);
+        put_u32(&mut descriptor, DATA_DESCRIPTOR_SIGNATURE);
+        put_u32(&mut descriptor, crc32);
+        put_u64(&mut descriptor, size);
+        put_u64(&mut descriptor, size);
+        self.write(&descriptor).await?;
+
+        self.entries.push(CentralDirectoryEntry {
+            name: name.to_string(),
+            modified,
+            crc32,
+            size,
+            offset,
+        });
+
+        Ok(())
+    }
+
+    /// Writes the central directory and flushes the writer
+    pub async fn finish(mut self) -> io::Result<W> {
+        let central_directory_offset = self.offset;
+
+        let entries = std::mem::take(&mut self.entries);
+        let mut central_directory = Vec::new();
+        for entry in &entries {
+            let (dos_time, dos_date) = to_dos_date_time(entry.modified);
+
+            let mut zip64_extra = Vec::new();
+            if entry.size >= ZIP64_LIMIT {
+                put_u64(&mut zip64_extra, entry.size);
+                put_u64(&mut zip64_extra, entry.size);
+            }
+            if entry.offset >= ZIP64_LIMIT {
+                put_u64(&mut zip64_extra, entry.offset);
+            }
+            let zip64_extra_length = if zip64_extra.is_empty() {
+                0
+            } else {
+                4 + zip64_extra.len()
+            };
+
+            put_u32(&mut central_directory, CENTRAL_DIRECTORY_SIGNATURE);
+            put_u16(&mut central_directory, VERSION_MADE_BY);
+            put_u16(&mut central_directory, VERSION_NEEDED);
+            put_u16(&mut central_directory, GENERAL_PURPOSE_FLAGS);
+            put_u16(&mut central_directory, 0); // Stored
+            put_u16(&mut central_directory, dos_time);
+            put_u16(&mut central_directory, dos_date);
+            put_u32(&mut central_directory, entry.crc32);
+            put_u32(&mut central_directory, entry.size.min(ZIP64_LIMIT) as u32);
+            put_u32(&mut central_directory, entry.size.min(ZIP64_LIMIT) as u32);
+            put_u16(&mut central_directory, entry.name.len() as u16);
+            put_u16(&mut central_directory, (zip64_extra_length + 9) as u16);
+            put_u16(&mut central_directory, 0); // Comment length
+            put_u16(&mut central_directory, 0); // Disk number
+            put_u16(&mut central_directory, 0); // Internal attributes
+            put_u32(&mut central_directory, EXTERNAL_ATTRIBUTES);
+            put_u32(&mut central_directory, entry.offset.min(ZIP64_LIMIT) as u32);
+            central_directory.extend_from_slice(entry.name.as_bytes());
+            if !zip64_extra.is_empty() {
+                put_u16(&mut central_directory, ZIP64_EXTRA_FIELD_ID);
+                put_u16(&mut central_directory, zip64_extra.len() as u16);
+                central_directory.extend_from_slice(&zip64_extra);
+            }
+            put_extended_timestamp(&mut central_directory, entry.modified);
+
+            // Avoid buffering the whole central directory of huge archives
+            if central_directory.len() >= 64 * 1024 {
+                self.write(&central_directory).await?;
+                central_directory.clear();
+            }
+        }
+        self.write(&central_directory).await?;
+
+        let central_directory_size = self.offset - central_directory_offset;
+        let entries_count = entries.len() as u64;
+
+        let mut end = Vec::with_capacity(98);
+        if entries_count >= u16::MAX as u64
+            || central_directory_size >= ZIP64_LIMIT
+            || central_directory_offset >= ZIP64_LIMIT
+        {
+            let zip64_end_offset = self.offset;
+
+            put_u32(&mut end, ZIP64_END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+            put_u64(&mut end, 44); // Size of the rest of this record
+            put_u16(&mut end, VERSION_MADE_BY);
+            put_u16(&mut end, VERSION_NEEDED);
+            put_u32(&mut end, 0); // Number of this disk
+            put_u32(&mut end, 0); // Disk where the central directory starts
+            put_u64(&mut end, entries_count);
+            put_u64(&mut end, entries_count);
+            put_u64(&mut end, central_directory_size);
+            put_u64(&mut end, central_directory_offset);
+
+            put_u32(&mut end, ZIP64_END_OF_CENTRAL_DIRECTORY_LOCATOR_SIGNATURE);
+            put_u32(&mut end, 0); // Disk with the ZIP64 end of central directory
+            put_u64(&mut end, zip64_end_offset);
+            put_u32(&mut end, 1); // Total number of disks
+        }
+
+        put_u32(&mut end, END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        put_u16(&mut end, 0); // Number of this disk
+        put_u16(&mut end, 0); // Disk where the central directory starts
+        put_u16(&mut end, entries_count.min(u16::MAX as u64) as u16);
+        put_u16(&mut end, entries_count.min(u16::MAX as u64) as u16);
+        put_u32(&mut end, central_directory_size.min(ZIP64_LIMIT) as u32);
+        put_u32(&mut end, central_directory_offset.min(ZIP64_LIMIT) as u32);
+        put_u16(&mut end, 0); // Comment length
+        self.write(&end).await?;
+
+        self.writer.flush().await?;
+        Ok(self.writer)
+    }
+
+    async fn write(&mut self, bytes: &[u8]) -> io::Result<()> {
+        self.writer.write_all(bytes).await?;
+        self.offset += bytes.len() as u64;
+        Ok(())
+    }
+}
+
+fn put_u16(buffer: &mut Vec<u8>, value: u16) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u32(buffer: &mut Vec<u8>, value: u32) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+fn put_u64(buffer: &mut Vec<u8>, value: u64) {
+    buffer.extend_from_slice(&value.to_le_bytes());
+}
+
+/// The modification time in UTC, as the MS-DOS one has no time zone and a 2 seconds precision
+fn put_extended_timestamp(buffer: &mut Vec<u8>, modified: OffsetDateTime) {
+    put_u16(buffer, EXTENDED_TIMESTAMP_EXTRA_FIELD_ID);
+    put_u16(buffer, 5);
+    buffer.push(1); // Only the modification time is present
+    let timestamp = modified.unix_timestamp().clamp(0, u32::MAX as i64);
+    put_u32(buffer, timestamp as u32);
+}
+
+/// Returns the MS-DOS `(time, date)`, which can only represent the years 1980 to 2107
+fn to_dos_date_time(date_time: OffsetDateTime) -> (u16, u16) {
+    let year = date_time.year();
+    if year < 1980 {
+        return (0, (1 << 5) | 1);
+    }
+    if year > 2107 {
+        return ((23 << 11) | (59 << 5) | 29, (127 << 9) | (12 << 5) | 31);
+    }
+
+    let time = ((date_time.hour() as u16) << 11)
+        | ((date_time.minute() as u16) << 5)
+        | (date_time.second() as u16 / 2);
+    let date = (((year - 1980) as u16) << 9)
+        | ((u8::from(date_time.month()) as u16) << 5)
+        | date_time.day() as u16;
+
+    (time, date)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use time::macros::datetime;
+
+    #[test]
+    fn dos_date_time() {
+        assert_eq!(
+            to_dos_date_time(datetime!(2024-07-15 13:45:31 UTC)),
+            ((13 << 11) | (45 << 5) | 15, (44 << 9) | (7 << 5) | 15)
+        );
+        assert_eq!(
+            to_dos_date_time(datetime!(1970-01-01 00:00:00 UTC)),
+            (0, (1 << 5) | 1)
+        );
+    }
+
+    #[tokio::test]
+    async fn archive_layout() {
+        let mut zip = ZipWriter::new(Vec::new());
+        let modified = datetime!(2024-07-15 13:45:30 UTC);
+        zip.add_file("Trip/a.jpg", modified, &b"hello"[..])
+            .await
+            .unwrap();
+        zip.add_file("b.jpg", modified, &b""[..]).await.unwrap();
+        let archive = zip.finish().await.unwrap();
+
+        let u32_at = |position: usize| {
+            u32::from_le_bytes(archive[position..position + 4].try_into().unwrap())
+        };
+
+        assert_eq!(u32_at(0), LOCAL_FILE_HEADER_SIGNATURE);
+        assert_eq!(u32_at(18), u32::MAX);
+        let name_end = 30 + "Trip/a.jpg".len();
+        assert_eq!(
+            u16::from_le_bytes([archive[name_end], archive[name_end + 1]]),
+            ZIP64_EXTRA_FIELD_ID
+        );
+        // Header, name, ZIP64 and extended timestamp extra fields and content
+        let descriptor = name_end + 20 + 9 + 5;
+        assert_eq!(u32_at(descriptor), DATA_DESCRIPTOR_SIGNATURE);
+        assert_eq!(u32_at(descriptor + 4), crc32fast::hash(b"hello"));
+        assert_eq!(u32_at(descriptor + 8), 5);
+        assert_eq!(u32_at(descriptor + 12), 0);
+        assert_eq!(u32_at(descriptor + 16), 5);
+
+        let end = archive.len() - 22;
+        assert_eq!(u32_at(end), END_OF_CENTRAL_DIRECTORY_SIGNATURE);
+        assert_eq!(
+            u16::from_le_bytes([archive[end + 10], archive[end + 11]]),
+            2
+        );
+        let central_directory_offset = u32_at(end + 16) as usize;
+        assert_eq!(
+            u32_at(central_directory_offset),
+            CENTRAL_DIRECTORY_SIGNATURE
+        );
+    }
+}